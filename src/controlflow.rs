@@ -0,0 +1,41 @@
+use crate::error::EvalError;
+use crate::identifier::Identifier;
+use crate::value::Value;
+
+/// interruption of the normal sequence of instructions,
+/// propagated until the enclosing loop or function call handles it
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+    Break(Option<Identifier>, Option<Value>),
+    Continue(Option<Identifier>),
+    Return(Value),
+    Error(EvalError),
+}
+
+impl ControlFlow {
+    /// true if this `break`/`continue` targets a loop labeled `label`
+    pub fn targets(&self, label: &Option<Identifier>) -> bool {
+        match self {
+            ControlFlow::Break(l, _) | ControlFlow::Continue(l) => l.is_none() || l == label,
+            _ => false,
+        }
+    }
+}
+
+impl From<EvalError> for ControlFlow {
+    fn from(e: EvalError) -> Self {
+        ControlFlow::Error(e)
+    }
+}
+
+/// a control flow signal reaching the top level is an error
+impl From<ControlFlow> for EvalError {
+    fn from(cf: ControlFlow) -> Self {
+        match cf {
+            ControlFlow::Break(label, _) => EvalError::BreakOutsideLoop(label),
+            ControlFlow::Continue(label) => EvalError::ContinueOutsideLoop(label),
+            ControlFlow::Return(_) => EvalError::ReturnOutsideFunction,
+            ControlFlow::Error(e) => e,
+        }
+    }
+}
@@ -2,15 +2,18 @@ use std::fmt::{self, Display};
 
 //use crate::memory::Address; // enlever ce commentaire une fois Address défini
 use crate::parsing::expression::Expression;
+use crate::parsing::instruction::Instruction;
 use crate::identifier::Identifier;
+use crate::parsing::pattern::Pattern;
 
 use crate::parser::ParseError;
-use crate::r#type::Type;
+use crate::r#type::{Type, IntType};
 
 #[derive(Debug)]
 pub enum Error {
     ParseError(ParseError),
     EvalError(EvalError),
+    TypeErrors(Vec<EvalError>),
 }
 
 #[derive(Debug, Clone)]
@@ -19,14 +22,64 @@ pub enum EvalError {
     DivisionByZero(Expression),
     Undefined(Identifier),
     AlreadyDefined(Identifier),
-    NotMutable(Option<Expression>),
-    TypeMismatch{expression: Expression, expected: Type, found: Option<Type>},
+    NotMutable(Option<Code>),
+    TypeMismatch{expression: Option<Code>, expected: Type, found: Option<Type>},
     NonAllocatedCell(Option<Expression>),
     NonInitializedValue(Option<Expression>),
     UseAfterFree(Option<Expression>),
     MovedValue(Option<Expression>),
     CannotMoveOwnedValue(Option<Expression>),
     CannotFreeOwnedValue(Option<Expression>),
+    /// `free` of a pointer to a variable or to a part of a value, not from `Ptr::new()`
+    CannotFreeNonHeapPointer(Option<Expression>),
+    WrongArgumentCount{function: Identifier, expected: usize, found: usize},
+    StackOverflow{depth: usize, calls: Vec<Identifier>},
+    BreakOutsideLoop(Option<Identifier>),
+    ContinueOutsideLoop(Option<Identifier>),
+    ReturnOutsideFunction,
+    BreakWithValue,
+    UndefinedMethod{expression: Expression, method: Identifier, r#type: Type},
+    StepByZero(Expression),
+    ShiftOverflow(Expression, IntType),
+    ArithmeticOverflow(Expression),
+    InvalidAssignment(Expression),
+    IndexOutOfBounds{expr: Expression, index: isize, len: usize},
+    NegativeLength{expression: Expression, len: isize},
+    UndefinedField{expression: Expression, field: Identifier, r#type: Type},
+    MissingField{expression: Expression, field: Identifier},
+    PatternMismatch{pattern: Box<Pattern>, expression: Expression, found: Type},
+    WrongVariantFields{expression: Expression, variant: String},
+    NoMatchingArm{expression: Expression, value: String},
+    NonExhaustiveMatch{expression: Expression, missing: Vec<String>},
+}
+
+/// code an error is reported on: an expression, or a statement as `x += 1`,
+/// boxed to keep `EvalError`, returned by most of the evaluation, small
+#[derive(Debug, Clone)]
+pub enum Code {
+    Expression(Box<Expression>),
+    Instruction(Box<Instruction>),
+}
+
+impl From<Expression> for Code {
+    fn from(e: Expression) -> Self {
+        Code::Expression(Box::new(e))
+    }
+}
+
+impl From<Instruction> for Code {
+    fn from(instr: Instruction) -> Self {
+        Code::Instruction(Box::new(instr))
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Code::Expression(e) => write!(f, "{}", e),
+            Code::Instruction(instr) => write!(f, "{}", instr),
+        }
+    }
 }
 
 impl From<ParseError> for Error {
@@ -48,9 +101,10 @@ impl Display for EvalError {
             DivisionByZero(e) => write!(f, "Division by zero, `{}` evaluates to 0", e),
             Undefined(id) => write!(f, "Undefined identifier `{}`.", id,),
             AlreadyDefined(id) => write!(f, "Identifier `{}` already defined.", id),
+            NotMutable(Some(Code::Instruction(instr))) => write!(f, "Cell is not mutable in `{}`.", instr),
             NotMutable(e) => write!(f, "Cell {}is not mutable.", e.as_ref().map(|e| format!("at `{}` ", e)).unwrap_or("".to_string())),
             TypeMismatch { expression, expected, found} => {
-                write!(f, "Type mismatch in expression `{}`. Expected: {}. {}", expression, expected, found.map(|f| format!("Found: {}", f)).unwrap_or("".to_string()))
+                write!(f, "Type mismatch{}. Expected: {}. {}", expression.as_ref().map(|e| format!(" in expression `{}`", e)).unwrap_or("".to_string()), expected, found.as_ref().map(|f| format!("Found: {}", f)).unwrap_or("".to_string()))
             },
             NonAllocatedCell(e) => write!(f, "Cell {}is not allocated.", e.as_ref().map(|e| format!("at `{}` ", e)).unwrap_or("".to_string())),
             NonInitializedValue(e) => write!(f, "Value {} is not initialized.", e.as_ref().map(|e| format!("in `{}` ", e)).unwrap_or("".to_string())),
@@ -59,16 +113,72 @@ impl Display for EvalError {
             MovedValue(e) => write!(f, "{} has been moved", e.as_ref().map(|e| format!("`{}`", e)).unwrap_or("value".to_string())),
             CannotMoveOwnedValue(e) => write!(f, "cannot move {}, owned value with move semantics", e.as_ref().map(|e| format!("`{}`", e)).unwrap_or("this value".to_string())),
             CannotFreeOwnedValue(e) => write!(f, "cannot free {}, owned value", e.as_ref().map(|e| format!("`{}`", e)).unwrap_or("this value".to_string())),
+            CannotFreeNonHeapPointer(e) => write!(f, "cannot free {}, not allocated by `Ptr::new()`", e.as_ref().map(|e| format!("`{}`", e)).unwrap_or("this pointer".to_string())),
+            WrongArgumentCount { function, expected, found } => write!(f, "Function `{}` takes {} argument(s) but {} were supplied.", function, expected, found),
+            StackOverflow { depth, calls } => write!(f, "Stack overflow: call depth exceeds {}. Calls: {}", depth, format_calls(calls)),
+            BreakOutsideLoop(None) => write!(f, "`break` outside of a loop."),
+            BreakOutsideLoop(Some(l)) => write!(f, "`break {}` outside of a loop labeled `{}`.", l, l),
+            ContinueOutsideLoop(None) => write!(f, "`continue` outside of a loop."),
+            ContinueOutsideLoop(Some(l)) => write!(f, "`continue {}` outside of a loop labeled `{}`.", l, l),
+            ReturnOutsideFunction => write!(f, "`return` outside of a function."),
+            BreakWithValue => write!(f, "`break` with value from a `while` or `for` loop, only `loop` can return a value."),
+            UndefinedMethod { expression, method, r#type } => write!(f, "No method `{}` for type {} in `{}`.", method, r#type, expression),
+            StepByZero(e) => write!(f, "`step_by` expects a positive step, `{}` is not.", e),
+            InvalidAssignment(e) => write!(f, "Invalid left-hand side of assignment `{}`.", e),
+            IndexOutOfBounds { expr, index, len } => write!(f, "Index out of bounds in `{}`: the len is {} but the index is {}.", expr, len, index),
+            NegativeLength { expression, len } => write!(f, "Array length `{}` evaluates to {}, it must not be negative.", expression, len),
+            UndefinedField { expression, field, r#type } => write!(f, "No field `{}` on type {} in `{}`.", field, r#type, expression),
+            MissingField { expression, field } => write!(f, "Missing field `{}` in `{}`.", field, expression),
+            WrongVariantFields { expression, variant } => write!(f, "`{}` does not match the fields of the variant `{}`.", expression, variant),
+            NoMatchingArm { expression, value } => write!(f, "No arm of the `match` matches `{}`, whose value is {}.", expression, value),
+            NonExhaustiveMatch { expression, missing } => write!(f, "Non-exhaustive patterns in `match {}`: {} not covered.", expression, format_patterns(missing)),
+            PatternMismatch { pattern, expression, found } => write!(f, "Pattern `{}` does not match `{}` of type {}.", pattern, expression, found),
+            ShiftOverflow(e, t) => write!(f, "Shift overflow in `{}`, the shift amount must be between 0 and {} for `{}`.", e, t.bits() - 1, t),
+            ArithmeticOverflow(e) => write!(f, "Arithmetic overflow in `{}`.", e),
         }
     }
 }
 
+/// `` `a` ``, `` `a` and `b` `` ou `` `a`, `b`, `c` and 2 more `` comme rustc
+fn format_patterns(patterns: &[String]) -> String {
+    const SHOWN: usize = 3;
+    let quoted: Vec<String> = patterns.iter().map(|p| format!("`{}`", p)).collect();
+    match quoted.len() {
+        1 => format!("pattern {}", quoted[0]),
+        n if n <= SHOWN + 1 => format!("patterns {} and {}", quoted[..n - 1].join(", "), quoted[n - 1]),
+        n => format!("patterns {} and {} more", quoted[..SHOWN].join(", "), n - SHOWN),
+    }
+}
+
+/// chaîne d'appels, les appels récursifs consécutifs sont regroupés (`f x999`)
+/// et seuls le début et la fin d'une longue chaîne sont affichés
+fn format_calls(calls: &[Identifier]) -> String {
+    const SHOWN: usize = 4;
+    let mut groups: Vec<(&Identifier, usize)> = vec![];
+    for call in calls {
+        match groups.last_mut() {
+            Some((id, n)) if *id == call => *n += 1,
+            _ => groups.push((call, 1)),
+        }
+    }
+    let mut res: Vec<String> = groups.iter()
+        .map(|(id, n)| if *n > 1 { format!("{} x{}", id, n) } else { id.to_string() })
+        .collect();
+    if res.len() > 2 * SHOWN {
+        let hidden: usize = groups[SHOWN..groups.len() - SHOWN].iter().map(|(_, n)| n).sum();
+        res.splice(SHOWN..res.len() - SHOWN, [format!("... {} calls ...", hidden)]);
+    }
+    res.join(" -> ")
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Error::*;
         match self {
             ParseError(e) => write!(f, "Parse Error: {}", e),
             EvalError(e) => write!(f, "Evaluation Error: {}", e),
+            // toutes les erreurs de type, une par ligne
+            TypeErrors(es) => write!(f, "{}", es.iter().map(|e| format!("Type Error: {}", e)).collect::<Vec<_>>().join("\n")),
         }
     }
 }
@@ -78,10 +188,25 @@ impl EvalError {
     pub fn with_expression_info(&self, e: Expression) -> Self {
         use EvalError::*;
         match self {
-            NotMutable(None) => NotMutable(Some(e)),
+            NotMutable(None) => NotMutable(Some(e.into())),
             NonAllocatedCell(None) => NonAllocatedCell(Some(e)),
             NonInitializedValue(None) => NonInitializedValue(Some(e)),
             UseAfterFree(None) => UseAfterFree(Some(e)),
+            MovedValue(None) => MovedValue(Some(e)),
+            CannotMoveOwnedValue(None) => CannotMoveOwnedValue(Some(e)),
+            CannotFreeOwnedValue(None) => CannotFreeOwnedValue(Some(e)),
+            CannotFreeNonHeapPointer(None) => CannotFreeNonHeapPointer(Some(e)),
+            TypeMismatch { expression: None, expected, found } => TypeMismatch { expression: Some(e.into()), expected: expected.clone(), found: found.clone() },
+            _ => self.clone(),
+        }
+    }
+
+    /// the errors about the statement as a whole, as `x += 1` on an immutable `x`
+    pub fn with_instruction_info(&self, instr: Instruction) -> Self {
+        use EvalError::*;
+        match self {
+            NotMutable(None) => NotMutable(Some(instr.into())),
+            TypeMismatch { expression: None, expected, found } => TypeMismatch { expression: Some(instr.into()), expected: expected.clone(), found: found.clone() },
             _ => self.clone(),
         }
     }
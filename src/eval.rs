@@ -2,237 +2,1231 @@
 use crate::identifier::Identifier;
 use crate::parsing::expression::Expression;
 use crate::parsing::instruction::Instruction;
+use crate::parsing::program::Program;
 use crate::pointer::Pointer;
+use crate::range::Range;
 use crate::value::Value;
 use Expression::*;
 use crate::parsing::binop::Binop;
+use crate::parsing::unop::Unop;
 use crate::error::EvalError;
+use crate::controlflow::ControlFlow;
 use crate::error::EvalError::TypeMismatch;
 use crate::memory::{ Address, Memory };
 use crate::namespace::NameSpace;
-use crate::r#type::Type;
+use crate::r#type::{Type, IntType};
+use crate::parsing::parsedvalue::ParsedValue;
+use crate::parsing::pattern::Pattern;
+use crate::parsing::structure::Fields;
 
 
 impl Expression {
 
-    fn eval_and_cast_to_int(&self, mem: &mut Memory) -> Result<isize, EvalError> {
+    /// an integer of any type used as an `isize`: an index, a length or a bound of a range
+    fn eval_and_cast_to_int(&self, mem: &mut Memory) -> Result<isize, ControlFlow> {
         let v = self.eval(mem)?;
-        v.to_int()
-         .map_err(|_| EvalError::TypeMismatch{
-            expression: self.clone(), 
-            expected: Type::Int, 
-            found: Some(Type::from(&v))})
+        match v.to_int() {
+            Ok(i) => Ok(i),
+            Err(Type::Int(_)) => Err(EvalError::ArithmeticOverflow(self.clone()).into()),
+            Err(t) => Err(EvalError::TypeMismatch{
+                expression: Some(self.clone().into()),
+                expected: Type::Int(IntType::Isize),
+                found: Some(t)}.into()),
+        }
+    }
+
+    /// value and suffix of an integer literal, `-1` and `-128i8` included
+    pub fn int_literal(&self) -> Option<(i128, Option<IntType>)> {
+        match self {
+            Const(ParsedValue::Integer(i, suffix)) => Some((*i, *suffix)),
+            UnOp(Unop::Neg, e) => e.int_literal().map(|(i, suffix)| (-i, suffix)),
+            _ => None,
+        }
+    }
+
+    /// value of an integer literal without suffix: its type is the one expected
+    pub fn untyped_literal(&self) -> Option<i128> {
+        match self.int_literal() {
+            Some((i, None)) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// evaluates the expression where a value of type `expected` is expected:
+    /// an integer literal without suffix gets the integer type expected
+    fn eval_as(&self, expected: &Type, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        match (self, expected) {
+            (_, Type::Int(t)) if self.untyped_literal().is_some() => Ok(int_value(self.untyped_literal().unwrap(), *t, self)?),
+            // le type attendu descend dans les composantes d'un tuple ou d'un tableau
+            (Tuple(es), Type::Tuple(ts)) if es.len() == ts.len() =>
+                Ok(Value::Tuple(es.iter().zip(ts).map(|(e, t)| e.eval_as(t, mem)).collect::<Result<_, _>>()?)),
+            (Array(es), Type::Array(t, _)) => eval_array(es, Some(t), mem),
+            (ArrayRepeat(e, n), Type::Array(t, _)) => eval_repeat(e, n, Some(t), mem),
+            // `let v: Vec<u8> = vec![1, 2]`
+            (VecMacro(e), Type::VecOf(t)) => match e.eval_as(&Type::Array(t.clone(), 0), mem)? {
+                Value::Array(vs) => Ok(mem.new_vec(vs, 0)),
+                _ => unreachable!("`vec!` is followed by an array"),
+            },
+            _ => self.eval(mem),
+        }
     }
-    fn eval_and_cast_to_bool(&self, mem: &mut Memory) -> Result<bool, EvalError> {
+    fn eval_and_cast_to_bool(&self, mem: &mut Memory) -> Result<bool, ControlFlow> {
         let v = self.eval(mem)?;
-        v.to_bool()
+        Ok(v.to_bool()
             .map_err(|_| EvalError::TypeMismatch{
-                expression: self.clone(),
+                expression: Some(self.clone().into()),
                 expected: Type::Bool,
-                found: Some(Type::from(&v))})
+                found: Some(Type::from(&v))})?)
     }
 
-    pub fn eval(&self, mem: &mut Memory) -> Result<Value, EvalError> {
+    pub fn eval(&self, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        mem.check_stack()?;
         match self {
-            Const(v) => Ok(Value::from(*v)),
-            Expression::Identifier(id) => Ok(mem.find(id)?),
+            // un littéral sans suffixe est un `isize`
+            Const(ParsedValue::Integer(..)) | UnOp(Unop::Neg, _) if self.int_literal().is_some() => {
+                let (i, suffix) = self.int_literal().unwrap();
+                Ok(int_value(i, suffix.unwrap_or_default(), self)?)
+            },
+            Const(v) => Ok(Value::from(v.clone())),
+            Expression::Identifier(id) => Ok(mem.take(id).map_err(|e| e.with_expression_info(self.clone()))?),
 
             BinOp(lhs, Binop::Add, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Integer(v1 + v2))
+                match lhs.eval(mem)? {
+                    // `s + t` réutilise la mémoire de `s`
+                    Value::String(p) => {
+                        let v2 = rhs.eval_borrow(mem)?;
+                        let res = concat(mem, &p, &v2, rhs);
+                        rhs.drop_temporary(mem, &v2);
+                        Ok(res?)
+                    },
+                    v @ Value::Str(_) => Err(TypeMismatch { expression: Some((*lhs.clone()).into()), expected: Type::String, found: Some(Type::from(&v)) }.into()),
+                    v1 => {
+                        let (i1, i2, t) = int_operands(lhs, v1, rhs, mem)?;
+                        Ok(eval_int_binop(Binop::Add, i1, i2, t, self, rhs)?)
+                    },
+                }
             },
-            BinOp(lhs, Binop::Sub, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Integer(v1 - v2))
-            }
-            BinOp(lhs, Binop::Mul, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Integer(v1 * v2))
-            }
-            BinOp(lhs, Binop::Div, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                if v2 == 0 { return Err(EvalError::DivisionByZero(*rhs.clone())) }
-                Ok(Value::Integer(v1 / v2))
+            BinOp(lhs, op @ (Binop::Sub | Binop::Mul | Binop::Div | Binop::Mod), rhs) => {
+                let v1 = lhs.eval(mem)?;
+                let (i1, i2, t) = int_operands(lhs, v1, rhs, mem)?;
+                Ok(eval_int_binop(*op, i1, i2, t, self, rhs)?)
             }
-            BinOp(lhs, Binop::Mod, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Integer(v1 % v2))
+
+            BinOp(lhs, op @ (Binop::Eq | Binop::Neq | Binop::Lt | Binop::Gt | Binop::Leq | Binop::Geq), rhs) => {
+                let v1 = lhs.eval_borrow(mem)?;
+                let (v1, v2) = eval_operands(lhs, v1, rhs, mem)?;
+                let ord = compare(mem, &v1, &v2, *op, rhs);
+                lhs.drop_temporary(mem, &v1);
+                rhs.drop_temporary(mem, &v2);
+                let ord = ord?;
+                Ok(Value::Boolean(match op {
+                    Binop::Eq => ord.is_eq(),
+                    Binop::Neq => ord.is_ne(),
+                    Binop::Lt => ord.is_lt(),
+                    Binop::Gt => ord.is_gt(),
+                    Binop::Leq => ord.is_le(),
+                    _ => ord.is_ge(),
+                }))
             }
+            // `rhs` n'est évalué que si `lhs` ne décide pas du résultat
+            BinOp(lhs, Binop::And, rhs) => Ok(Value::Boolean(lhs.eval_and_cast_to_bool(mem)? && rhs.eval_and_cast_to_bool(mem)?)),
+            BinOp(lhs, Binop::Or, rhs) => Ok(Value::Boolean(lhs.eval_and_cast_to_bool(mem)? || rhs.eval_and_cast_to_bool(mem)?)),
 
-            BinOp(lhs, Binop::Eq, rhs) => {
-                let v1 = lhs.eval(mem)?;
-                let v2 = rhs.eval(mem)?;
-                if Type::from(&v1) != Type::from(&v2) { return Err(TypeMismatch {expression: *rhs.clone(), expected: Type::from(&v1), found: Some(Type::from(&v2))}) }
-                match (v1, v2) {
-                    (Value::Boolean(b1), Value::Boolean(b2)) => Ok(Value::Boolean(b1 == b2)),
-                    (Value::Integer(i1), Value::Integer(i2)) => Ok(Value::Boolean(i1 == i2)),
-                    _ => unreachable!()
+            UnOp(Unop::Neg, e) => {
+                match e.eval(mem)? {
+                    // `-x` déborde pour `x == MIN`, et pour tout entier non signé sauf 0
+                    Value::Integer(i, t) => Ok(int_value(-i, t, self)?),
+                    v => Err(TypeMismatch { expression: Some((*e.clone()).into()), expected: Type::Int(IntType::Isize), found: Some(Type::from(&v)) }.into()),
                 }
             }
-            BinOp(lhs, Binop::Neq, rhs) => {
-                let v1 = lhs.eval(mem)?;
-                let v2 = rhs.eval(mem)?;
-                if Type::from(&v1) != Type::from(&v2) { return Err(TypeMismatch {expression: *rhs.clone(), expected: Type::from(&v1), found: Some(Type::from(&v2))}) }
-                match (v1, v2) {
-                    (Value::Boolean(b1), Value::Boolean(b2)) => Ok(Value::Boolean(b1 != b2)),
-                    (Value::Integer(i1), Value::Integer(i2)) => Ok(Value::Boolean(i1 != i2)),
-                    _ => unreachable!()
+            UnOp(Unop::Not, e) => {
+                match e.eval(mem)? {
+                    Value::Boolean(b) => Ok(Value::Boolean(!b)),
+                    Value::Integer(i, t) => Ok(Value::Integer(t.wrap(!i), t)),
+                    v => Err(TypeMismatch { expression: Some((*e.clone()).into()), expected: Type::Bool, found: Some(Type::from(&v)) }.into())
                 }
             }
 
-
-            BinOp(lhs, Binop::Leq, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Boolean(v1 <= v2))
+            // `&`, `|` et `^` s'appliquent aux entiers bit à bit et aux booléens sans court-circuit
+            BinOp(lhs, op @ (Binop::BitAnd | Binop::BitOr | Binop::BitXor), rhs) => {
+                let v1 = lhs.eval(mem)?;
+                let (v1, v2) = eval_operands(lhs, v1, rhs, mem)?;
+                match (&v1, &v2) {
+                    (Value::Integer(i1, t1), Value::Integer(i2, t2)) if t1 == t2 => Ok(eval_int_binop(*op, *i1, *i2, *t1, self, rhs)?),
+                    (Value::Boolean(b1), Value::Boolean(b2)) => Ok(Value::Boolean(match op {
+                        Binop::BitAnd => b1 & b2,
+                        Binop::BitOr => b1 | b2,
+                        _ => b1 ^ b2,
+                    })),
+                    (Value::Integer(..) | Value::Boolean(_), _) =>
+                        Err(TypeMismatch {expression: Some((*rhs.clone()).into()), expected: Type::from(&v1), found: Some(Type::from(&v2))}.into()),
+                    _ => Err(TypeMismatch {expression: Some((*lhs.clone()).into()), expected: Type::Int(IntType::Isize), found: Some(Type::from(&v1))}.into()),
+                }
             }
-            BinOp(lhs, Binop::Geq, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Boolean(v1 >= v2))
+            // le décalage peut être de n'importe quel type entier
+            BinOp(lhs, op @ (Binop::Shl | Binop::Shr), rhs) => {
+                let (i, t) = match lhs.eval(mem)? {
+                    Value::Integer(i, t) => (i, t),
+                    v => return Err(TypeMismatch { expression: Some((*lhs.clone()).into()), expected: Type::Int(IntType::Isize), found: Some(Type::from(&v)) }.into()),
+                };
+                let n = rhs.eval_and_cast_to_int(mem)?;
+                Ok(eval_shift(*op, i, t, n, self)?)
             }
-            BinOp(lhs, Binop::Lt, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Boolean(v1 < v2))
+
+            Block(instrs, tail) => {
+                mem.push(NameSpace::new());
+                // les items d'un bloc ne sont visibles que dans le bloc
+                let outer = instrs.iter().any(Instruction::is_item).then(|| mem.push_items());
+                let res = declare_items(instrs, mem)
+                    .and_then(|_| instrs.iter().filter(|instr| !instr.is_item()).try_for_each(|instr| instr.exec_statement(mem)))
+                    .and_then(|_| tail.as_ref().map_or(Ok(Value::Unit), |e| e.eval(mem)));
+                if let Some(outer) = outer { mem.pop_items(outer) }
+                mem.pop();
+                res
             }
-            BinOp(lhs, Binop::Gt, rhs) => {
-                let v1 = lhs.eval_and_cast_to_int(mem)?;
-                let v2 = rhs.eval_and_cast_to_int(mem)?;
-                Ok(Value::Boolean(v1 > v2))
+            // les types des deux branches sont vérifiés avant l'exécution, par `check_types`
+            IfElse { cond, cond_true, cond_false } => {
+                let res = cond.eval_and_cast_to_bool(mem)?;
+                let v = if res { cond_true } else { cond_false }.eval(mem)?;
+                // sans `else`, une valeur dont le type n'est connu qu'à l'exécution (`v[0]`) doit être `()`
+                if res && !matches!(v, Value::Unit) && matches!(&**cond_false, Block(instrs, None) if instrs.is_empty()) {
+                    return Err(TypeMismatch { expression: Some(cond_true.tail_expr().unwrap_or(cond_true).clone().into()), expected: Type::Unit, found: Some(Type::from(&v)) }.into())
+                }
+                Ok(v)
             }
-            BinOp(lhs, Binop::And, rhs) => {
-                let v1 = lhs.eval_and_cast_to_bool(mem)?;
-                let v2 = rhs.eval_and_cast_to_bool(mem)?;
-                Ok(Value::Boolean(v1 && v2))
+            Call(id, args) => {
+                let fun = mem.find_function(id)?;
+                if fun.params.len() != args.len() {
+                    return Err(EvalError::WrongArgumentCount { function: id.clone(), expected: fun.params.len(), found: args.len() }.into())
+                }
+                // les arguments sont évalués dans l'environnement de l'appelant
+                let mut frame = NameSpace::new();
+                for (param, arg) in fun.params.iter().zip(args) {
+                    let v = arg.eval_as(&param.r#type, mem)?;
+                    let found = mem.type_as(&v, &param.r#type);
+                    if found != param.r#type { return Err(TypeMismatch { expression: Some(arg.clone().into()), expected: param.r#type.clone(), found: Some(found) }.into()) }
+                    frame.declare(&param.id, param.mutable, v)?;
+                }
+                mem.push_frame(id, frame)?;
+                let res = fun.body.eval(mem);
+                mem.pop_frame();
+                let v = match (res, &*fun.body) {
+                    // `fn f() -> u8 { 1 }` : le littéral final prend le type de retour,
+                    // sauf si le corps est quitté par `return`
+                    (Ok(_), Block(_, Some(tail))) if tail.untyped_literal().is_some() => tail.eval_as(&fun.ret, mem)?,
+                    (Ok(v) | Err(ControlFlow::Return(v)), _) => v,
+                    // break et continue ne traversent pas les appels de fonction
+                    (Err(cf), _) => return Err(ControlFlow::Error(cf.into())),
+                };
+                let found = mem.type_as(&v, &fun.ret);
+                if found != fun.ret { return Err(TypeMismatch { expression: Some(self.clone().into()), expected: fun.ret.clone(), found: Some(found) }.into()) }
+                Ok(v)
+            },
+            // les `break` d'une même boucle sont typés avant l'exécution, par `check_types`
+            Loop { label, body } => {
+                loop {
+                    match body.eval(mem) {
+                        Err(cf @ ControlFlow::Break(..)) if cf.targets(label) => {
+                            let ControlFlow::Break(_, v) = cf else { unreachable!() };
+                            return Ok(v.unwrap_or(Value::Unit))
+                        },
+                        Err(cf @ ControlFlow::Continue(_)) if cf.targets(label) => continue,
+                        res => { res?; }
+                    }
+                }
+            },
+            Expression::Range { start, end, inclusive } => {
+                // les bornes sont du même type, un littéral sans suffixe prend celui de l'autre
+                let (v1, v2) = match (start.untyped_literal(), end.untyped_literal()) {
+                    (Some(_), None) => {
+                        let v2 = end.eval(mem)?;
+                        (start.eval_as(&Type::from(&v2), mem)?, v2)
+                    },
+                    _ => {
+                        let v1 = start.eval(mem)?;
+                        let v2 = end.eval_as(&Type::from(&v1), mem)?;
+                        (v1, v2)
+                    },
+                };
+                match (v1, v2) {
+                    (Value::Integer(a, t1), Value::Integer(b, t2)) if t1 == t2 => Ok(Value::Range(Range::new(a, b, *inclusive, t1))),
+                    (Value::Integer(_, t1), v2) => Err(TypeMismatch { expression: Some((*end.clone()).into()), expected: Type::Int(t1), found: Some(Type::from(&v2)) }.into()),
+                    (v1, _) => Err(TypeMismatch { expression: Some((*start.clone()).into()), expected: Type::Int(IntType::Isize), found: Some(Type::from(&v1)) }.into()),
+                }
+            },
+            MethodCall(e, method, args) => {
+                let v = e.eval_borrow(mem)?;
+                let res = self.call_method(&v, method, args, mem);
+                e.drop_temporary(mem, &v);
+                res
+            },
+            AssocCall(r#type, id, args) => {
+                let args_count = |expected: usize| {
+                    if args.len() == expected { Ok(()) }
+                    else { Err(EvalError::WrongArgumentCount { function: id.clone(), expected, found: args.len() }) }
+                };
+                // `Enum::Variant(...)`
+                if mem.find_enum(r#type).is_ok() {
+                    return self.eval_variant(r#type, id, &Fields::Tuple(args.clone()), mem)
+                }
+                match (r#type.to_string().as_str(), id.to_string().as_str()) {
+                    ("String", "new") => {
+                        args_count(0)?;
+                        Ok(mem.new_string(""))
+                    },
+                    ("String", "from") => {
+                        args_count(1)?;
+                        match args[0].eval(mem)? {
+                            Value::Str(s) => Ok(mem.new_string(&s)),
+                            v @ Value::String(_) => Ok(v),
+                            v => Err(TypeMismatch { expression: Some(args[0].clone().into()), expected: Type::Str, found: Some(Type::from(&v)) }.into()),
+                        }
+                    },
+                    ("Vec", "new") => {
+                        args_count(0)?;
+                        Ok(mem.new_vec(vec![], 0))
+                    },
+                    ("Vec", "with_capacity") => {
+                        args_count(1)?;
+                        let cap = args[0].eval_and_cast_to_int(mem)?;
+                        let cap = usize::try_from(cap).map_err(|_| EvalError::NegativeLength { expression: args[0].clone(), len: cap })?;
+                        Ok(mem.new_vec(vec![], cap))
+                    },
+                    _ => Err(EvalError::Undefined(Identifier::from(format!("{}::{}", r#type, id).as_str())).into()),
+                }
+            },
+            Index(e, index) if e.is_place() => {
+                let (addr, _) = e.eval_place(mem)?;
+                let i = index.eval_and_cast_to_int(mem)?;
+                match self.element_address(addr.clone(), i, mem)? {
+                    Some(addr) => {
+                        let v = mem.value_at(&addr).map_err(|e| e.with_expression_info(self.clone()))?;
+                        // comme en Rust, on ne sort pas une valeur possédée d'un tableau
+                        if v.is_owned() { return Err(EvalError::CannotMoveOwnedValue(Some(self.clone())).into()) }
+                        Ok(v)
+                    },
+                    None => {
+                        let v = mem.value_at(&addr).map_err(|err| err.with_expression_info(*e.clone()))?;
+                        self.eval_index(&v, i, mem)
+                    },
+                }
+            },
+            Index(e, index) => match e.eval(mem)? {
+                // l'élément d'un tableau temporaire est déplacé, les autres sont libérés
+                Value::Array(mut vs) => {
+                    let i = index.eval_and_cast_to_int(mem);
+                    let res = i.and_then(|i| match usize::try_from(i).ok().filter(|i| *i < vs.len()) {
+                        Some(i) => Ok(vs.remove(i)),
+                        None => Err(EvalError::IndexOutOfBounds { expr: self.clone(), index: i, len: vs.len() }.into()),
+                    });
+                    vs.iter().for_each(|v| mem.drop_value(v));
+                    res
+                },
+                v => {
+                    let res = index.eval_and_cast_to_int(mem).and_then(|i| self.eval_index(&v, i, mem));
+                    mem.drop_value(&v);
+                    res
+                },
+            },
+            Tuple(es) => Ok(Value::Tuple(es.iter().map(|e| e.eval(mem)).collect::<Result<_, _>>()?)),
+            Array(es) => eval_array(es, None, mem),
+            ArrayRepeat(e, n) => eval_repeat(e, n, None, mem),
+            VecMacro(e) => match e.eval(mem)? {
+                Value::Array(vs) => Ok(mem.new_vec(vs, 0)),
+                _ => unreachable!("`vec!` is followed by an array"),
+            },
+            Struct(name, fields) => {
+                let def = mem.find_struct(name)?;
+                Ok(Value::Struct(name.clone(), self.eval_fields(&def.fields, fields, name, mem)?))
+            },
+            Variant(name, variant, fields) => self.eval_variant(name, variant, fields, mem),
+            Match(e, arms) => {
+                // la valeur filtrée n'est déplacée que si le bras choisi lie une valeur possédée
+                let (v, place) = if e.is_place() {
+                    let (addr, deref) = e.eval_place(mem)?;
+                    (mem.value_at(&addr).map_err(|err| err.with_expression_info(*e.clone()))?, Some((addr, deref)))
+                } else {
+                    (e.eval(mem)?, None)
+                };
+                for arm in arms {
+                    if !arm.pattern.matches(&v, mem)? { continue }
+                    // chaque bras lie ses variables dans un nouvel espace de noms
+                    mem.push(NameSpace::new());
+                    let guard = arm.pattern.bind(v.clone(), mem).map_err(ControlFlow::from)
+                        .and_then(|_| arm.guard.as_ref().map_or(Ok(true), |g| g.eval_and_cast_to_bool(mem)));
+                    match guard {
+                        Ok(true) => (),
+                        res => { mem.discard(); res?; continue },
+                    }
+                    let moved = arm.pattern.moves(&v);
+                    if let (true, Some((addr, deref))) = (moved, &place) {
+                        if *deref { mem.discard(); return Err(EvalError::CannotMoveOwnedValue(Some(*e.clone())).into()) }
+                        mem.take_at(addr).map_err(|err| err.with_expression_info(*e.clone()))?;
+                    }
+                    if moved || place.is_none() { arm.pattern.unbound_parts(&v).iter().for_each(|v| mem.drop_value(v)) }
+                    let res = arm.body.eval(mem);
+                    mem.pop();
+                    return res
+                }
+                let value = mem.show(&v);
+                if place.is_none() { mem.drop_value(&v) }
+                Err(EvalError::NoMatchingArm { expression: *e.clone(), value }.into())
+            },
+            Field(e, field) => {
+                let (addr, deref) = if e.is_place() { e.eval_place(mem)? } else {
+                    match e.eval(mem)? {
+                        Value::Pointer(p) => (p.get_address().clone(), true),
+                        v => return self.component(v, field, mem),
+                    }
+                };
+                let (addr, deref) = self.component_address(addr, deref, field, mem)?;
+                let v = mem.value_at(&addr).map_err(|e| e.with_expression_info(self.clone()))?;
+                // on ne peut pas sortir une valeur possédée de derrière un pointeur
+                if v.is_owned() && deref { return Err(EvalError::CannotMoveOwnedValue(Some(self.clone())).into()) }
+                Ok(mem.take_at(&addr).map_err(|e| e.with_expression_info(self.clone()))?)
+            },
+            NewPtr => Ok(Value::Pointer(Pointer::new(NewPtr.eval_to_address(mem)?))),
+
+            Deref(p) => {
+                let v = self.eval_deref(p, mem)?;
+                // on ne peut pas sortir une valeur possédée de derrière un pointeur
+                if v.is_owned() { return Err(EvalError::CannotMoveOwnedValue(Some(self.clone())).into()) }
+                Ok(v)
+            },
+
+            AmpersAnd(p) => Ok(Value::Pointer(Pointer::new(p.eval_to_address(mem)?))),
+        }
+    }
+
+    /// evaluates without moving out of a variable or a pointer: the value is only read,
+    /// as for the operands of a comparison or the receiver of a method
+    pub fn eval_borrow(&self, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        match self {
+            Expression::Identifier(id) => Ok(mem.find(id).map_err(|e| e.with_expression_info(self.clone()))?),
+            Deref(p) => self.eval_deref(p, mem),
+            Field(..) if self.is_place() => {
+                let (addr, _) = self.eval_place(mem)?;
+                Ok(mem.value_at(&addr).map_err(|e| e.with_expression_info(self.clone()))?)
+            },
+            // l'élément d'un tableau est lu sans être déplacé
+            Index(e, index) if e.is_place() => {
+                let (addr, _) = e.eval_place(mem)?;
+                let i = index.eval_and_cast_to_int(mem)?;
+                match self.element_address(addr.clone(), i, mem)? {
+                    Some(addr) => Ok(mem.value_at(&addr).map_err(|e| e.with_expression_info(self.clone()))?),
+                    None => {
+                        let v = mem.value_at(&addr).map_err(|err| err.with_expression_info(*e.clone()))?;
+                        self.eval_index(&v, i, mem)
+                    },
+                }
+            },
+            _ => self.eval(mem),
+        }
+    }
+
+    /// drops a borrowed value if it is a temporary, not a value read in memory
+    pub fn drop_temporary(&self, mem: &mut Memory, v: &Value) {
+        if !self.is_place() { mem.drop_value(v) }
+    }
+
+    /// true if the expression designates memory: a variable, `*p`, or a component or an element of a place
+    pub fn is_place(&self) -> bool {
+        match self {
+            Expression::Identifier(_) | Deref(_) => true,
+            Field(e, _) | Index(e, _) => e.is_place(),
+            _ => false,
+        }
+    }
+
+    /// fields `f: e` of a struct literal, in the declaration order of `defs`
+    fn eval_fields(&self, defs: &[(Identifier, Type)], inits: &[(Identifier, Expression)], name: &Identifier, mem: &mut Memory) -> Result<Vec<(Identifier, Value)>, ControlFlow> {
+        let mut values = vec![None; defs.len()];
+        for (id, e) in inits {
+            let index = defs.iter().position(|(f, _)| f == id)
+                .ok_or(EvalError::UndefinedField { expression: self.clone(), field: id.clone(), r#type: Type::Named(name.clone()) })?;
+            if values[index].is_some() { return Err(EvalError::AlreadyDefined(id.clone()).into()) }
+            let expected = &defs[index].1;
+            let v = e.eval_as(expected, mem)?;
+            let found = mem.type_as(&v, expected);
+            if found != *expected { return Err(TypeMismatch { expression: Some(e.clone().into()), expected: expected.clone(), found: Some(found) }.into()) }
+            values[index] = Some(v);
+        }
+        match defs.iter().zip(&values).find(|(_, v)| v.is_none()) {
+            Some(((id, _), _)) => Err(EvalError::MissingField { expression: self.clone(), field: id.clone() }.into()),
+            None => Ok(defs.iter().map(|(id, _)| id.clone()).zip(values.into_iter().flatten()).collect()),
+        }
+    }
+
+    /// `Enum::Variant`, `Enum::Variant(...)` or `Enum::Variant { ... }`
+    fn eval_variant(&self, name: &Identifier, variant: &Identifier, fields: &Fields<Expression>, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        let def = mem.find_enum(name)?;
+        let var = def.variants.iter().find(|v| v.name == *variant)
+            .ok_or(EvalError::Undefined(Identifier::from(format!("{}::{}", name, variant).as_str())))?;
+        let v = match (&var.fields, fields) {
+            (Fields::Unit, Fields::Unit) => Value::Unit,
+            (Fields::Tuple(types), Fields::Tuple(es)) if types.len() == es.len() => {
+                let mut vs = vec![];
+                for (t, e) in types.iter().zip(es) {
+                    let v = e.eval_as(t, mem)?;
+                    let found = mem.type_as(&v, t);
+                    if found != *t { return Err(TypeMismatch { expression: Some(e.clone().into()), expected: t.clone(), found: Some(found) }.into()) }
+                    vs.push(v);
+                }
+                Value::Tuple(vs)
+            },
+            (Fields::Struct(defs), Fields::Struct(inits)) => Value::Struct(variant.clone(), self.eval_fields(defs, inits, name, mem)?),
+            _ => return Err(EvalError::WrongVariantFields { expression: self.clone(), variant: format!("{}::{}", name, var) }.into()),
+        };
+        Ok(Value::Variant(name.clone(), variant.clone(), Box::new(v)))
+    }
+
+    /// address of a place, and true if it is reached through a pointer:
+    /// an owned value cannot be moved out of it
+    fn eval_place(&self, mem: &mut Memory) -> Result<(Address, bool), ControlFlow> {
+        match self {
+            Field(e, field) => {
+                let (addr, deref) = e.eval_place(mem)?;
+                self.component_address(addr, deref, field, mem)
+            },
+            // comme derrière un pointeur, on ne sort pas une valeur possédée d'un tableau
+            Index(e, index) => {
+                let (addr, _) = e.eval_place(mem)?;
+                let i = index.eval_and_cast_to_int(mem)?;
+                match self.element_address(addr, i, mem)? {
+                    Some(addr) => Ok((addr, true)),
+                    None => Err(EvalError::InvalidAssignment(self.clone()).into()),
+                }
+            },
+            Deref(_) => Ok((self.eval_to_address(mem)?, true)),
+            _ => Ok((self.eval_to_address(mem)?, false)),
+        }
+    }
+
+    /// address of the element `i` of the array at `addr`, `p[i]` is `(*p)[i]`,
+    /// the elements of a `Vec` are in its buffer; none if there is no array, as for the bytes of a string
+    fn element_address(&self, mut addr: Address, i: isize, mem: &Memory) -> Result<Option<Address>, ControlFlow> {
+        loop {
+            if let Some(len) = mem.array_len(&addr) {
+                return match usize::try_from(i).ok().filter(|i| *i < len) {
+                    Some(i) => Ok(Some(mem.element_address(&addr, i))),
+                    None => Err(EvalError::IndexOutOfBounds { expr: self.clone(), index: i, len }.into()),
+                }
             }
-            BinOp(lhs, Binop::Or, rhs) => {
-                let v1 = lhs.eval_and_cast_to_bool(mem)?;
-                let v2 = rhs.eval_and_cast_to_bool(mem)?;
-                Ok(Value::Boolean(v1 || v2))
+            match mem.value_at(&addr) {
+                Ok(Value::Pointer(p) | Value::Vec(p)) => addr = p.get_address().clone(),
+                _ => return Ok(None),
             }
+        }
+    }
 
-            Conditional{ cond, cond_true, cond_false } => {
-                let res = cond.eval_and_cast_to_bool(mem)?;
-                if res { Ok(cond_true.eval(mem)?) }
-                else { Ok(cond_false.eval(mem)?) }
+    /// address of the component `field` of the value at `addr`, `p.f` is `(*p).f`
+    fn component_address(&self, mut addr: Address, mut deref: bool, field: &Identifier, mem: &Memory) -> Result<(Address, bool), ControlFlow> {
+        loop {
+            if let Some(addr) = mem.field_address(&addr, field) { return Ok((addr, deref)) }
+            match mem.value_at(&addr).map_err(|e| e.with_expression_info(self.clone()))? {
+                Value::Pointer(p) => { addr = p.get_address().clone(); deref = true },
+                v => return Err(EvalError::UndefinedField { expression: self.clone(), field: field.clone(), r#type: Type::from(&v) }.into()),
             }
-            NewPtr => Ok(Value::Pointer(Pointer::new(NewPtr.eval_to_address(mem)?))),
+        }
+    }
 
-            Deref(id) => { 
-                let val = id.eval(mem)?;
-                match val {
-                    Value::Pointer(addr) => {
-                        let res = mem.value_at(addr.get_address());
-                        match res {
-                            Ok(_) => res,
-                            Err(EvalError::NonInitializedValue(_)) => Err(EvalError::NonInitializedValue(Some(*id.clone()))),
-                            Err(EvalError::NonAllocatedCell(_)) => Err(EvalError::NonAllocatedCell(Some(*id.clone()))),
-                            _ => unreachable!()
-                        }
-                    },
-                    _ => Err(TypeMismatch{expression: self.clone(), expected: Type::Pointer, found: Some(Type::from(&val))})
+    /// component of a temporary tuple or struct, the other components are dropped
+    fn component(&self, v: Value, field: &Identifier, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        let r#type = Type::from(&v);
+        match v.into_components() {
+            Ok((shape, mut vs)) if shape.index(field).is_some_and(|i| i < vs.len()) => {
+                let component = vs.remove(shape.index(field).unwrap());
+                vs.iter().for_each(|v| mem.drop_value(v));
+                Ok(component)
+            },
+            Ok((_, vs)) => {
+                vs.iter().for_each(|v| mem.drop_value(v));
+                Err(EvalError::UndefinedField { expression: self.clone(), field: field.clone(), r#type }.into())
+            },
+            Err(_) => Err(EvalError::UndefinedField { expression: self.clone(), field: field.clone(), r#type }.into()),
+        }
+    }
+
+    fn eval_deref(&self, id: &Expression, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        let val = id.eval(mem)?;
+        match val {
+            Value::Pointer(addr) => {
+                let res = mem.value_at(addr.get_address());
+                match res {
+                    Ok(v) => Ok(v),
+                    Err(EvalError::NonInitializedValue(_)) => Err(EvalError::NonInitializedValue(Some(id.clone())).into()),
+                    Err(EvalError::NonAllocatedCell(_)) => Err(EvalError::NonAllocatedCell(Some(id.clone())).into()),
+                    Err(e) => Err(e.with_expression_info(id.clone()).into()),
                 }
             },
-            
-            AmpersAnd(p) => Ok(Value::Pointer(Pointer::new(p.eval_to_address(mem)?))),
+            _ => Err(TypeMismatch{expression: Some(self.clone().into()), expected: Type::Pointer, found: Some(Type::from(&val))}.into())
+        }
+    }
+
+    fn call_method(&self, v: &Value, method: &Identifier, args: &[Expression], mem: &mut Memory) -> Result<Value, ControlFlow> {
+        let args_count = |expected: usize| {
+            if args.len() == expected { Ok(()) }
+            else { Err(EvalError::WrongArgumentCount { function: method.clone(), expected, found: args.len() }) }
+        };
+        match (v, method.to_string().as_str()) {
+            (Value::Range(r), "rev") => {
+                args_count(0)?;
+                Ok(Value::Range(r.rev()))
+            },
+            (Value::Range(r), "step_by") => {
+                args_count(1)?;
+                let n = args[0].eval_and_cast_to_int(mem)?;
+                if n <= 0 { return Err(EvalError::StepByZero(args[0].clone()).into()) }
+                Ok(Value::Range(r.step_by(n as usize)))
+            },
+            (Value::Integer(i1, t), name) if int_method(name).is_some() => {
+                args_count(1)?;
+                let (mode, op) = int_method(name).unwrap();
+                let i2 = match args[0].eval_as(&Type::Int(*t), mem)? {
+                    Value::Integer(i2, t2) if t2 == *t => i2,
+                    v => return Err(TypeMismatch { expression: Some(args[0].clone().into()), expected: Type::Int(*t), found: Some(Type::from(&v)) }.into()),
+                };
+                Ok(eval_int_method(mode, op, *i1, i2, *t, &args[0])?)
+            },
+            (Value::Array(vs), "len") => {
+                args_count(0)?;
+                Ok(Value::Integer(vs.len() as i128, IntType::Usize))
+            },
+            (Value::Vec(p), "len" | "capacity") => {
+                args_count(0)?;
+                let (_, len, cap) = mem.vec_block(p).map_err(|e| e.with_expression_info(self.clone()))?;
+                Ok(Value::Integer(if method.to_string() == "len" { len } else { cap } as i128, IntType::Usize))
+            },
+            (Value::Vec(p), "push" | "insert") => {
+                let push = method.to_string() == "push";
+                args_count(if push { 1 } else { 2 })?;
+                self.check_receiver_mutable(mem)?;
+                let (_, len, _) = mem.vec_block(p).map_err(|e| e.with_expression_info(self.clone()))?;
+                let index = if push { len as isize } else { args[0].eval_and_cast_to_int(mem)? };
+                let index = usize::try_from(index).ok().filter(|i| *i <= len)
+                    .ok_or(EvalError::IndexOutOfBounds { expr: self.clone(), index, len })?;
+                let e = args.last().unwrap();
+                // les éléments d'un `Vec` sont tous du même type
+                let first = if len > 0 { Some(mem.value_at(&mem.element_address(p.get_address(), 0))?) } else { None };
+                let v = match &first {
+                    Some(first) => e.eval_as(&Type::from(first), mem)?,
+                    None => e.eval(mem)?,
+                };
+                if let Some(first) = first {
+                    if Type::from(&first) != Type::from(&v) {
+                        mem.drop_value(&v);
+                        return Err(TypeMismatch { expression: Some(e.clone().into()), expected: Type::from(&first), found: Some(Type::from(&v)) }.into())
+                    }
+                }
+                mem.vec_insert(p, index, v)?;
+                Ok(Value::Unit)
+            },
+            // `v.pop()` donne `Option::None` si `v` est vide
+            (Value::Vec(p), "pop") => {
+                args_count(0)?;
+                self.check_receiver_mutable(mem)?;
+                let (_, len, _) = mem.vec_block(p).map_err(|e| e.with_expression_info(self.clone()))?;
+                if len == 0 { return Ok(Value::option(None)) }
+                Ok(Value::option(Some(mem.vec_remove(p, len - 1)?)))
+            },
+            (Value::Vec(p), "remove") => {
+                args_count(1)?;
+                self.check_receiver_mutable(mem)?;
+                let (_, len, _) = mem.vec_block(p).map_err(|e| e.with_expression_info(self.clone()))?;
+                let index = args[0].eval_and_cast_to_int(mem)?;
+                let index = usize::try_from(index).ok().filter(|i| *i < len)
+                    .ok_or(EvalError::IndexOutOfBounds { expr: self.clone(), index, len })?;
+                Ok(mem.vec_remove(p, index)?)
+            },
+            (Value::Str(_) | Value::String(_), "len") => {
+                args_count(0)?;
+                Ok(Value::Integer(mem.text(v)?.unwrap().len() as i128, IntType::Usize))
+            },
+            (Value::Str(_) | Value::String(_), "to_string") => {
+                args_count(0)?;
+                let text = mem.text(v)?.unwrap();
+                Ok(mem.new_string(&text))
+            },
+            (Value::Char(c), "to_string") => {
+                args_count(0)?;
+                Ok(mem.new_string(&c.to_string()))
+            },
+            _ => Err(EvalError::UndefinedMethod { expression: self.clone(), method: method.clone(), r#type: Type::from(v) }.into())
         }
     }
 
-    fn eval_to_address(&self, mem: &mut Memory) -> Result<Address, EvalError> {
+    /// the receiver of a method modifying a `Vec` must be mutable
+    fn check_receiver_mutable(&self, mem: &Memory) -> Result<(), EvalError> {
+        match self {
+            MethodCall(e, ..) => e.check_mutable(mem),
+            _ => Ok(()),
+        }
+    }
+
+    /// the variable a place is rooted in, `v` for `v[i].f`, none for `*p`
+    fn root_variable(&self) -> Option<&Identifier> {
+        match self {
+            Expression::Identifier(id) => Some(id),
+            Field(e, _) | Index(e, _) => e.root_variable(),
+            _ => None,
+        }
+    }
+
+    /// the buffer of a `Vec` is in the heap, where every cell is mutable:
+    /// a `Vec` is only modified through a mutable variable, or through a pointer
+    fn check_mutable(&self, mem: &Memory) -> Result<(), EvalError> {
+        let Some(id) = self.root_variable() else { return Ok(()) };
+        let addr = mem.get_address(id)?;
+        if mem.is_mutable(&addr)? || matches!(mem.find(id), Ok(Value::Pointer(_))) { Ok(()) }
+        else { Err(EvalError::NotMutable(Some(self.clone().into()))) }
+    }
+
+    /// `v[i]`, a string is indexed by byte
+    fn eval_index(&self, v: &Value, i: isize, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        match mem.text(v)? {
+            Some(text) => match usize::try_from(i).ok().and_then(|i| text.as_bytes().get(i)) {
+                Some(b) => Ok(Value::Integer(*b as i128, IntType::U8)),
+                None => Err(EvalError::IndexOutOfBounds { expr: self.clone(), index: i, len: text.len() }.into()),
+            },
+            None => Err(TypeMismatch { expression: Some(self.clone().into()), expected: Type::Str, found: Some(Type::from(v)) }.into()),
+        }
+    }
+
+    fn eval_to_address(&self, mem: &mut Memory) -> Result<Address, ControlFlow> {
         match self {
             NewPtr => Ok(mem.malloc()),
-            Expression::Identifier(i) => mem.get_address(i),
-            _ => Err(TypeMismatch {expression: self.clone(), expected: Type::Pointer, found: None})
+            Expression::Identifier(i) => Ok(mem.get_address(i)?),
+            Deref(p) => match p.eval(mem)? {
+                Value::Pointer(ptr) => Ok(ptr.get_address().clone()),
+                v => Err(TypeMismatch { expression: Some((*p.clone()).into()), expected: Type::Pointer, found: Some(Type::from(&v)) }.into()),
+            },
+            Field(..) | Index(..) if self.is_place() => Ok(self.eval_place(mem)?.0),
+            _ => Err(TypeMismatch {expression: Some(self.clone().into()), expected: Type::Pointer, found: None}.into())
+        }
+    }
+}
+
+
+/// `[a, b, c]`, the first element has the type `expected` if one is given
+fn eval_array(es: &[Expression], expected: Option<&Type>, mem: &mut Memory) -> Result<Value, ControlFlow> {
+    let mut vs: Vec<Value> = vec![];
+    for e in es {
+        let v = match (vs.first(), expected) {
+            (Some(first), _) => e.eval_as(&Type::from(first), mem)?,
+            (None, Some(t)) => e.eval_as(t, mem)?,
+            (None, None) => e.eval(mem)?,
+        };
+        // les éléments d'un tableau sont tous du même type
+        if let Some(first) = vs.first().filter(|first| Type::from(*first) != Type::from(&v)) {
+            return Err(TypeMismatch { expression: Some(e.clone().into()), expected: Type::from(first), found: Some(Type::from(&v)) }.into())
+        }
+        vs.push(v);
+    }
+    Ok(Value::Array(vs))
+}
+
+/// `[e; n]`, the element has the type `expected` if one is given
+fn eval_repeat(e: &Expression, n: &Expression, expected: Option<&Type>, mem: &mut Memory) -> Result<Value, ControlFlow> {
+    let v = match expected {
+        Some(t) => e.eval_as(t, mem)?,
+        None => e.eval(mem)?,
+    };
+    let len = n.eval_and_cast_to_int(mem)?;
+    let len = usize::try_from(len).map_err(|_| EvalError::NegativeLength { expression: n.clone(), len })?;
+    // une valeur possédée ne peut pas être copiée dans plusieurs éléments
+    if v.is_owned() && len != 1 {
+        mem.drop_value(&v);
+        return Err(EvalError::CannotMoveOwnedValue(Some(e.clone())).into())
+    }
+    Ok(Value::Array(vec![v; len]))
+}
+
+/// memory designated by the left-hand side of an assignment
+enum Place {
+    Var(Identifier),
+    Address(Address),
+}
+
+impl Place {
+    fn read(&self, mem: &Memory) -> Result<Value, EvalError> {
+        match self {
+            Place::Var(id) => mem.find(id),
+            Place::Address(addr) => mem.value_at(addr),
+        }
+    }
+
+    /// type of the values the place may hold, none for a heap cell never written
+    fn r#type(&self, mem: &Memory) -> Result<Option<Type>, EvalError> {
+        match self {
+            Place::Var(id) => mem.type_at(&mem.get_address(id)?),
+            Place::Address(addr) => mem.type_at(addr),
+        }
+    }
+
+    fn write(&self, mem: &mut Memory, v: &Value) -> Result<(), EvalError> {
+        match self {
+            Place::Var(id) => mem.write_var(id, v),
+            Place::Address(addr) => mem.write_at(addr, v.clone()),
         }
     }
 }
 
+/// `s + v` or `s += v`, the text of `v` is appended to the `String` `s` in place
+fn concat(mem: &mut Memory, p: &Pointer, v: &Value, rhs: &Expression) -> Result<Value, EvalError> {
+    // `&t` est accepté comme `&str` quand `t` est une `String`
+    let v = match v {
+        Value::Pointer(q) => mem.value_at(q.get_address()).map_err(|e| e.with_expression_info(rhs.clone()))?,
+        v => v.clone(),
+    };
+    match (&v, mem.text(&v)?) {
+        (Value::Str(_) | Value::String(_), Some(text)) => {
+            let s = Value::String(p.clone());
+            let res = format!("{}{}", mem.text(&s)?.unwrap(), text);
+            mem.set_text(p, &res)?;
+            Ok(s)
+        },
+        _ => Err(TypeMismatch { expression: Some(rhs.clone().into()), expected: Type::Str, found: Some(Type::from(&v)) }),
+    }
+}
+
+/// ordering of `v1` and `v2` for `op`, only `==` and `!=` are defined on pointers and ranges
+fn compare(mem: &Memory, v1: &Value, v2: &Value, op: Binop, rhs: &Expression) -> Result<std::cmp::Ordering, EvalError> {
+    use std::cmp::Ordering;
+    let mismatch = || TypeMismatch { expression: Some(rhs.clone().into()), expected: Type::from(v1), found: Some(Type::from(v2)) };
+    match (v1, v2) {
+        (Value::Integer(i1, t1), Value::Integer(i2, t2)) if t1 == t2 => Ok(i1.cmp(i2)),
+        (Value::Boolean(b1), Value::Boolean(b2)) => Ok(b1.cmp(b2)),
+        (Value::Char(c1), Value::Char(c2)) => Ok(c1.cmp(c2)),
+        (Value::Unit, Value::Unit) => Ok(Ordering::Equal),
+        // ordre lexicographique, comme en Rust
+        (Value::Tuple(vs1), Value::Tuple(vs2)) | (Value::Array(vs1), Value::Array(vs2)) if vs1.len() == vs2.len() => {
+            for (v1, v2) in vs1.iter().zip(vs2) {
+                match compare(mem, v1, v2, op, rhs)? {
+                    Ordering::Equal => (),
+                    ord => return Ok(ord),
+                }
+            }
+            Ok(Ordering::Equal)
+        },
+        // ordre lexicographique, puis le plus court d'abord
+        (Value::Vec(p1), Value::Vec(p2)) => {
+            let (Value::Array(vs1), Value::Array(vs2)) = (mem.value_at(p1.get_address())?, mem.value_at(p2.get_address())?) else { unreachable!("a Vec points to its buffer") };
+            for (v1, v2) in vs1.iter().zip(&vs2) {
+                match compare(mem, v1, v2, op, rhs)? {
+                    Ordering::Equal => (),
+                    ord => return Ok(ord),
+                }
+            }
+            Ok(vs1.len().cmp(&vs2.len()))
+        },
+        (Value::Variant(n1, var1, fs1), Value::Variant(n2, var2, fs2)) if n1 == n2 && matches!(op, Binop::Eq | Binop::Neq) =>
+            Ok(if var1 == var2 && compare(mem, fs1, fs2, op, rhs)?.is_eq() { Ordering::Equal } else { Ordering::Less }),
+        // les structures ne sont que comparées pour l'égalité
+        (Value::Struct(n1, fs1), Value::Struct(n2, fs2)) if n1 == n2 && matches!(op, Binop::Eq | Binop::Neq) => {
+            for ((_, v1), (_, v2)) in fs1.iter().zip(fs2) {
+                if compare(mem, v1, v2, op, rhs)?.is_ne() { return Ok(Ordering::Less) }
+            }
+            Ok(Ordering::Equal)
+        },
+        (Value::Pointer(_), Value::Pointer(_)) | (Value::Range(_), Value::Range(_)) if matches!(op, Binop::Eq | Binop::Neq) =>
+            Ok(if v1 == v2 { Ordering::Equal } else { Ordering::Less }),
+        (Value::Pointer(_) | Value::Range(_), _) =>
+            Err(TypeMismatch { expression: Some(rhs.clone().into()), expected: Type::Int(IntType::Isize), found: Some(Type::from(v1)) }),
+        // `String` et `&str` se comparent par leur texte
+        _ => match (mem.text(v1)?, mem.text(v2)?) {
+            (Some(s1), Some(s2)) => Ok(s1.cmp(&s2)),
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+/// `place op= rhs` once both sides are evaluated to `v1` and `v2`, the type errors are reported
+/// on the whole statement `instr`, the overflows on the operation `place op rhs`
+fn eval_compound(instr: &Instruction, op: Binop, v1: Value, v2: Value, place: &Expression, rhs: &Expression) -> Result<Value, EvalError> {
+    let expr = || BinOp(Box::new(place.clone()), op, Box::new(rhs.clone()));
+    match (&v1, &v2) {
+        (Value::Integer(i1, t), Value::Integer(i2, _)) if matches!(op, Binop::Shl | Binop::Shr) =>
+            eval_shift(op, *i1, *t, isize::try_from(*i2).unwrap_or(isize::MAX), &expr()),
+        (Value::Integer(i1, t1), Value::Integer(i2, t2)) if t1 == t2 => eval_int_binop(op, *i1, *i2, *t1, &expr(), rhs),
+        (Value::Boolean(b1), Value::Boolean(b2)) if matches!(op, Binop::BitAnd | Binop::BitOr | Binop::BitXor) => {
+            match op {
+                Binop::BitAnd => Ok(Value::Boolean(b1 & b2)),
+                Binop::BitOr => Ok(Value::Boolean(b1 | b2)),
+                _ => Ok(Value::Boolean(b1 ^ b2)),
+            }
+        }
+        (Value::Boolean(_), _) if matches!(op, Binop::BitAnd | Binop::BitOr | Binop::BitXor) =>
+            Err(TypeMismatch { expression: Some(instr.clone().into()), expected: Type::Bool, found: Some(Type::from(&v2)) }),
+        (Value::Integer(..), _) =>
+            Err(TypeMismatch { expression: Some(instr.clone().into()), expected: Type::from(&v1), found: Some(Type::from(&v2)) }),
+        _ => Err(TypeMismatch { expression: Some(instr.clone().into()), expected: Type::Int(IntType::Isize), found: Some(Type::from(&v1)) }),
+    }
+}
+
+/// `i` as a value of type `t`, an overflow if it does not fit
+fn int_value(i: i128, t: IntType, expr: &Expression) -> Result<Value, EvalError> {
+    if t.contains(i) { Ok(Value::Integer(i, t)) } else { Err(EvalError::ArithmeticOverflow(expr.clone())) }
+}
+
+/// evaluates `rhs` without moving it, `lhs` being evaluated to `v1`: an integer literal
+/// without suffix gets the type of the other operand, as `1` in `x + 1` with `x: u8`
+fn eval_operands(lhs: &Expression, v1: Value, rhs: &Expression, mem: &mut Memory) -> Result<(Value, Value), ControlFlow> {
+    let v2 = match rhs.untyped_literal() {
+        Some(_) => rhs.eval_as(&Type::from(&v1), mem)?,
+        None => rhs.eval_borrow(mem)?,
+    };
+    let v1 = match lhs.untyped_literal() {
+        Some(_) => lhs.eval_as(&Type::from(&v2), mem)?,
+        None => v1,
+    };
+    Ok((v1, v2))
+}
+
+/// operands of an arithmetic operator, integers of the same type
+fn int_operands(lhs: &Expression, v1: Value, rhs: &Expression, mem: &mut Memory) -> Result<(i128, i128, IntType), ControlFlow> {
+    if !matches!(v1, Value::Integer(..)) {
+        return Err(TypeMismatch { expression: Some(lhs.clone().into()), expected: Type::Int(IntType::Isize), found: Some(Type::from(&v1)) }.into())
+    }
+    match eval_operands(lhs, v1, rhs, mem)? {
+        (Value::Integer(i1, t1), Value::Integer(i2, t2)) if t1 == t2 => Ok((i1, i2, t1)),
+        (v1, v2) => Err(TypeMismatch { expression: Some(rhs.clone().into()), expected: Type::from(&v1), found: Some(Type::from(&v2)) }.into()),
+    }
+}
+
+/// `i1 op i2` on integers of type `t`, checked as by Rust in debug mode:
+/// a result that does not fit in `t` is an overflow
+fn eval_int_binop(op: Binop, i1: i128, i2: i128, t: IntType, expr: &Expression, rhs: &Expression) -> Result<Value, EvalError> {
+    let res = match op {
+        Binop::Add => i1.checked_add(i2),
+        Binop::Sub => i1.checked_sub(i2),
+        Binop::Mul => i1.checked_mul(i2),
+        Binop::Div | Binop::Mod if i2 == 0 => return Err(EvalError::DivisionByZero(rhs.clone())),
+        Binop::Div => Some(i1 / i2),
+        // `MIN % -1` déborde comme `MIN / -1`
+        Binop::Mod => Some(i1 % i2).filter(|_| t.contains(i1 / i2)),
+        Binop::BitAnd => Some(i1 & i2),
+        Binop::BitOr => Some(i1 | i2),
+        Binop::BitXor => Some(i1 ^ i2),
+        _ => unreachable!("`{}` is not an arithmetic operator", op),
+    };
+    int_value(res.ok_or(EvalError::ArithmeticOverflow(expr.clone()))?, t, expr)
+}
+
+/// mode and operator of the methods `wrapping_*`, `checked_*` and `saturating_*`
+pub fn int_method(name: &str) -> Option<(&str, Binop)> {
+    let (mode, op) = name.split_once('_')?;
+    let op = match op {
+        "add" => Binop::Add,
+        "sub" => Binop::Sub,
+        "mul" => Binop::Mul,
+        "div" => Binop::Div,
+        "rem" => Binop::Mod,
+        _ => return None,
+    };
+    match mode {
+        "wrapping" | "checked" => Some((mode, op)),
+        "saturating" if op != Binop::Mod => Some((mode, op)),
+        _ => None,
+    }
+}
+
+/// `i1 op i2` by the method `mode_op`: a result that does not fit in `t` wraps around,
+/// gives `Option::None`, or is clamped to the bounds of `t`
+fn eval_int_method(mode: &str, op: Binop, i1: i128, i2: i128, t: IntType, rhs: &Expression) -> Result<Value, EvalError> {
+    if i2 == 0 && matches!(op, Binop::Div | Binop::Mod) {
+        return if mode == "checked" { Ok(Value::option(None)) } else { Err(EvalError::DivisionByZero(rhs.clone())) }
+    }
+    // le résultat exact, sauf un produit trop grand pour un i128
+    let exact = match op {
+        Binop::Add => i1.checked_add(i2),
+        Binop::Sub => i1.checked_sub(i2),
+        Binop::Mul => i1.checked_mul(i2),
+        Binop::Div => Some(i1 / i2),
+        _ => Some(i1 % i2),
+    };
+    // `MIN % -1` déborde comme `MIN / -1`
+    let overflow = exact.is_none_or(|i| !t.contains(i)) || !t.contains(i1 / i2) && op == Binop::Mod;
+    match mode {
+        "wrapping" => Ok(Value::Integer(t.wrap(exact.unwrap_or(i1.wrapping_mul(i2))), t)),
+        "checked" => Ok(Value::option(exact.filter(|_| !overflow).map(|i| Value::Integer(i, t)))),
+        _ => Ok(Value::Integer(match exact {
+            Some(i) => t.saturate(i),
+            None if (i1 < 0) != (i2 < 0) => t.min(),
+            None => t.max(),
+        }, t)),
+    }
+}
+
+/// `i << n` or `i >> n`, the shift amount must be less than the number of bits of `t`;
+/// comme en Rust, les bits sortis à gauche sont perdus
+fn eval_shift(op: Binop, i: i128, t: IntType, n: isize, expr: &Expression) -> Result<Value, EvalError> {
+    match u32::try_from(n).ok().filter(|n| *n < t.bits()) {
+        Some(n) if op == Binop::Shl => Ok(Value::Integer(t.wrap(i.wrapping_shl(n)), t)),
+        Some(n) => Ok(Value::Integer(i >> n, t)),
+        None => Err(EvalError::ShiftOverflow(expr.clone(), t)),
+    }
+}
+
+impl Expression {
+    /// expression giving the value of the expression, if any
+    pub fn tail_expr(&self) -> Option<&Expression> {
+        match self {
+            Block(_, tail) => tail.as_ref().and_then(|e| e.tail_expr()),
+            IfElse { cond_true, .. } => cond_true.tail_expr(),
+            e => Some(e),
+        }
+    }
+}
 
 impl Instruction {
+    /// true for the definition of a function, a struct or an enum
+    pub fn is_item(&self) -> bool {
+        matches!(self, Instruction::FnDef(_) | Instruction::StructDef(_) | Instruction::EnumDef(_))
+    }
+
+    /// runs an instruction whose value is not used: a temporary value is dropped
+    pub fn exec_statement(&self, mem: &mut Memory) -> Result<(), ControlFlow> {
+        let (_, v) = self.exec(mem)?;
+        if let Instruction::Expr(_) = self { mem.drop_value(&v) }
+        Ok(())
+    }
+
     #[allow(unused)]
-    pub fn exec(&self, mem: &mut Memory) -> Result<(Option<Identifier>, Value), EvalError> {
+    pub fn exec(&self, mem: &mut Memory) -> Result<(Option<Identifier>, Value), ControlFlow> {
         match self {
-            Instruction::Let{id, mutable, expr} => {
-                let v_temp = expr.eval(mem)?;
-                mem.declare(id, *mutable, v_temp.clone())?;
-                Ok((Some(id.clone()), v_temp))
+            Instruction::Let{pattern, r#type, expr} => {
+                let v_temp = match r#type {
+                    Some(t) => expr.eval_as(t, mem)?,
+                    None => expr.eval(mem)?,
+                };
+                if let Some(t) = r#type.as_ref().filter(|t| **t != mem.type_as(&v_temp, t)) {
+                    return Err(TypeMismatch { expression: Some(expr.clone().into()), expected: t.clone(), found: Some(mem.type_as(&v_temp, t)) }.into())
+                }
+                if !pattern.matches(&v_temp, mem)? {
+                    return Err(EvalError::PatternMismatch { pattern: Box::new(pattern.clone()), expression: expr.clone(), found: Type::from(&v_temp) }.into())
+                }
+                pattern.bind(v_temp.clone(), mem)?;
+                pattern.unbound_parts(&v_temp).iter().for_each(|v| mem.drop_value(v));
+                match pattern {
+                    Pattern::Identifier { id, .. } => Ok((Some(id.clone()), v_temp)),
+                    _ => Ok((None, v_temp)),
+                }
             },
             Instruction::Expr(expr) => {
                 Ok((None, expr.eval(mem)?))
             }
-            Instruction::Block(instrs) => {
-                mem.push(NameSpace::new());
-                let mut return_value = Value::Unit;
-                for instr in instrs {
-                    let (_, val) = instr.exec(mem).map_err(|err| {mem.pop(); err})?;
-                    return_value = val;
-                };
-                mem.pop();
-                Ok((None, return_value))
-            }
-
-            Instruction::IfElse { cond, cond_true, cond_false } => {
-                let res = cond.eval_and_cast_to_bool(mem)?;
-                if res { cond_true.exec(mem) }
-                else { cond_false.exec(mem) }
+            Instruction::While{label, cond, body} => {
+                while cond.eval_and_cast_to_bool(mem)? {
+                    match body.eval(mem) {
+                        Err(cf @ ControlFlow::Break(_, Some(_))) if cf.targets(label) => return Err(EvalError::BreakWithValue.into()),
+                        Err(cf @ ControlFlow::Break(_, None)) if cf.targets(label) => break,
+                        Err(cf @ ControlFlow::Continue(_)) if cf.targets(label) => continue,
+                        res => { res?; }
+                    }
+                }
+                Ok((None, Value::Unit))
             },
 
-            Instruction::While(cond, inst) => {
-                let mut res = cond.eval_and_cast_to_bool(mem)?;
-                while res {
-                    inst.exec(mem)?;
-                    res = cond.eval_and_cast_to_bool(mem)?;
+            Instruction::For{label, id, iter, body} => {
+                let v = iter.eval(mem)?;
+                let range = v.to_range().map_err(|t| TypeMismatch { expression: Some(iter.clone().into()), expected: Type::Range(IntType::Isize), found: Some(t) })?;
+                for i in range.iter() {
+                    // une nouvelle liaison immuable à chaque itération
+                    let mut ns = NameSpace::new();
+                    ns.declare(id, false, Value::Integer(i, range.int_type()))?;
+                    mem.push(ns);
+                    let res = body.eval(mem);
+                    mem.pop();
+                    match res {
+                        Err(cf @ ControlFlow::Break(_, Some(_))) if cf.targets(label) => return Err(EvalError::BreakWithValue.into()),
+                        Err(cf @ ControlFlow::Break(_, None)) if cf.targets(label) => break,
+                        Err(cf @ ControlFlow::Continue(_)) if cf.targets(label) => continue,
+                        res => { res?; }
+                    }
                 }
                 Ok((None, Value::Unit))
             },
 
-            Instruction::WriteAt(e1, e2) => {
-                let mut res_final: Result<(Option<Identifier>, Value), EvalError> = Ok((None, Value::Unit));
-                match e1 {
-                    Expression::Deref(id) => {
-                        let val = id.eval(mem)?;
-                        match val {
-                            Value::Pointer(addr) => { 
-                                let r_val = e2.eval(mem)?; 
-                                let res = mem.write_at(addr.get_address(), r_val.clone());
-                                match res {
-                                    Ok(_) => res_final = Ok((None, r_val)),
-                                    Err(EvalError::NonAllocatedCell(_)) => res_final = Err(EvalError::NonAllocatedCell(Some(e1.clone()))),
-                                    Err(EvalError::NotMutable(_)) => res_final = Err(EvalError::NotMutable(Some(e1.clone()))),
-                                    _ => unreachable!()
-                                }
-                            },
-                            _ => todo!()
-                        }
-                    }
-                    Expression::Identifier(id) => {
-                        let val = e2.eval(mem)?;
-                        let res = mem.write_var(&id, &val);
-                        match res {
-                            Ok(_) => res_final = Ok((Some(id.clone()), val.clone())),
-                            Err(EvalError::NonAllocatedCell(_)) => res_final = Err(EvalError::NonAllocatedCell(Some(e1.clone()))),
-                            Err(EvalError::NotMutable(_)) => res_final = Err(EvalError::NotMutable(Some(e1.clone()))),
-                            Err(EvalError::Undefined(id)) => res_final = Err(EvalError::Undefined(id.clone())),
-                            Err(EvalError::TypeMismatch { .. }) => todo!(),
-                            _ => unreachable!()
-                        }
-                    }
-                    _ => todo!()
+            Instruction::WriteAt(e1, op, e2) => {
+                // un littéral sans suffixe est évalué après l'emplacement, dont il prend le type
+                let val = match e2.untyped_literal() {
+                    Some(_) => None,
+                    None => Some(e2.eval(mem)?),
+                };
+                // l'emplacement n'est évalué qu'une fois, même pour `e1 op= e2`
+                let place = match e1 {
+                    Expression::Identifier(id) => Place::Var(id.clone()),
+                    _ if e1.is_place() => {
+                        let addr = e1.eval_to_address(mem)?;
+                        e1.check_mutable(mem).map_err(|err| match op {
+                            Some(_) => EvalError::NotMutable(Some(self.clone().into())),
+                            None => err,
+                        })?;
+                        Place::Address(addr)
+                    },
+                    _ => return Err(EvalError::InvalidAssignment(e1.clone()).into())
+                };
+                let mut val = match val {
+                    Some(v) => v,
+                    None => match place.r#type(mem).map_err(|err| err.with_expression_info(e1.clone()))? {
+                        Some(t) => e2.eval_as(&t, mem)?,
+                        None => e2.eval(mem)?,
+                    },
+                };
+                if let Some(op) = op {
+                    let old = place.read(mem).map_err(|err| err.with_expression_info(e1.clone()))?;
+                    val = match (op, old) {
+                        (Binop::Add, Value::String(p)) => concat(mem, &p, &val, e2)?,
+                        (op, old) => eval_compound(self, *op, old, val, e1, e2)?,
+                    };
+                }
+                // une valeur du mauvais type est désignée par `e2`, les autres erreurs par `e1`,
+                // ou par toute l'instruction pour `e1 op= e2`
+                place.write(mem, &val).map_err(|err| match (err, op) {
+                    (err, Some(_)) => err.with_instruction_info(self.clone()).with_expression_info(e1.clone()),
+                    (err @ TypeMismatch { .. }, None) => err.with_expression_info(e2.clone()),
+                    (err, None) => err.with_expression_info(e1.clone()),
+                })?;
+                match place {
+                    Place::Var(id) => Ok((Some(id), val)),
+                    Place::Address(_) => Ok((None, val)),
                 }
-                res_final
             },
             
             Instruction::Free(e) => {
-                let id_val = e.eval(mem)?;
-                mem.free(&id_val)?;
+                let id_val = e.eval_borrow(mem)?;
+                mem.free(&id_val).map_err(|err| err.with_expression_info(e.clone()))?;
                 Ok((None, Value::Unit))
             },
+
+            Instruction::FnDef(fun) => {
+                mem.declare_function(fun)?;
+                Ok((Some(fun.name.clone()), Value::Unit))
+            },
+            Instruction::StructDef(def) => {
+                mem.declare_struct(def)?;
+                Ok((Some(def.name.clone()), Value::Unit))
+            },
+            Instruction::EnumDef(def) => {
+                mem.declare_enum(def)?;
+                Ok((Some(def.name.clone()), Value::Unit))
+            },
+
+            Instruction::Break(label, expr) => {
+                let v = match expr {
+                    Some(e) => Some(e.eval(mem)?),
+                    None => None,
+                };
+                Err(ControlFlow::Break(label.clone(), v))
+            },
+            Instruction::Continue(label) => Err(ControlFlow::Continue(label.clone())),
+            Instruction::Return(expr) => {
+                let v = match expr {
+                    Some(e) => e.eval(mem)?,
+                    None => Value::Unit,
+                };
+                Err(ControlFlow::Return(v))
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+impl Pattern {
+    /// true if the value matches the pattern, the guard of an arm aside
+    fn matches(&self, v: &Value, mem: &Memory) -> Result<bool, EvalError> {
+        Ok(match (self, v) {
+            (Pattern::Identifier { .. } | Pattern::Wildcard, _) => true,
+            // un littéral `&str` filtre aussi une `String`
+            (Pattern::Literal(ParsedValue::Str(s)), v) => mem.text(v)?.is_some_and(|t| t == *s),
+            // un littéral entier filtre un entier de tout type
+            (Pattern::Literal(ParsedValue::Integer(i, _)), Value::Integer(j, _)) => i == j,
+            (Pattern::Literal(lit), v) => Value::from(lit.clone()) == *v,
+            (Pattern::Range { start, end, inclusive }, v) => match (start, end, v) {
+                (ParsedValue::Integer(a, _), ParsedValue::Integer(b, _), Value::Integer(i, _)) => a <= i && (i < b || *inclusive && i == b),
+                (ParsedValue::Char(a), ParsedValue::Char(b), Value::Char(c)) => a <= c && (c < b || *inclusive && c == b),
+                _ => false,
+            },
+            (Pattern::Tuple(ps), Value::Tuple(vs)) => ps.len() == vs.len() && Pattern::all_match(ps.iter().zip(vs), mem)?,
+            (Pattern::Struct { name, fields, rest }, Value::Struct(n, fvs)) => {
+                let mut matched = vec![];
+                for (id, p) in fields {
+                    match fvs.iter().find(|(f, _)| f == id) {
+                        Some((_, v)) => matched.push((p, v)),
+                        None => return Ok(false),
+                    }
+                }
+                name == n && (*rest || fields.len() == fvs.len()) && Pattern::all_match(matched.into_iter(), mem)?
+            },
+            (Pattern::Variant(name, variant, fields), Value::Variant(n, var, fv)) => name == n && variant == var && match fields {
+                None => **fv == Value::Unit,
+                Some(p) => p.matches(fv, mem)?,
+            },
+            _ => false,
+        })
+    }
+
+    fn all_match<'a>(mut pvs: impl Iterator<Item = (&'a Pattern, &'a Value)>, mem: &Memory) -> Result<bool, EvalError> {
+        pvs.try_fold(true, |acc, (p, v)| Ok(acc && p.matches(v, mem)?))
+    }
+
+    /// declares the variables of the pattern, which must match the value
+    fn bind(&self, v: Value, mem: &mut Memory) -> Result<(), EvalError> {
+        match (self, v) {
+            (Pattern::Identifier { id, mutable }, v) => mem.declare(id, *mutable, v),
+            (Pattern::Tuple(ps), Value::Tuple(vs)) => ps.iter().zip(vs).try_for_each(|(p, v)| p.bind(v, mem)),
+            (Pattern::Struct { fields, .. }, Value::Struct(_, fvs)) => fields.iter().try_for_each(|(id, p)| {
+                let (_, v) = fvs.iter().find(|(f, _)| f == id).unwrap();
+                p.bind(v.clone(), mem)
+            }),
+            (Pattern::Variant(_, _, Some(p)), Value::Variant(_, _, fv)) => p.bind(*fv, mem),
+            // `_` et les littéraux ne lient rien
+            _ => Ok(()),
+        }
+    }
+
+    /// owned parts of the value that no variable of the pattern gets,
+    /// they are dropped when the value is moved into the pattern
+    fn unbound_parts(&self, v: &Value) -> Vec<Value> {
+        match (self, v) {
+            (Pattern::Identifier { .. }, _) => vec![],
+            (Pattern::Tuple(ps), Value::Tuple(vs)) => ps.iter().zip(vs).flat_map(|(p, v)| p.unbound_parts(v)).collect(),
+            (Pattern::Struct { fields, .. }, Value::Struct(_, fvs)) => fvs.iter().flat_map(|(f, v)| {
+                match fields.iter().find(|(id, _)| id == f) {
+                    Some((_, p)) => p.unbound_parts(v),
+                    None => v.owned_parts(),
+                }
+            }).collect(),
+            (Pattern::Variant(_, _, Some(p)), Value::Variant(_, _, fv)) => p.unbound_parts(fv),
+            (_, v) => v.owned_parts(),
+        }
+    }
+
+    /// true if a variable of the pattern gets an owned part of the value, which is then moved
+    fn moves(&self, v: &Value) -> bool {
+        self.unbound_parts(v).len() < v.owned_parts().len()
+    }
+}
+
+/// functions, structs and enums are declared before running the statements of a block,
+/// so that they can be used before their definition
+fn declare_items(instrs: &[Instruction], mem: &mut Memory) -> Result<(), ControlFlow> {
+    for instr in instrs {
+        match instr {
+            Instruction::FnDef(fun) => mem.declare_function(fun)?,
+            Instruction::StructDef(def) => mem.declare_struct(def)?,
+            Instruction::EnumDef(def) => mem.declare_enum(def)?,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+impl Program {
+    pub fn exec(&self, mem: &mut Memory) -> Result<Value, ControlFlow> {
+        declare_items(&self.instrs, mem)?;
+        for instr in self.instrs.iter().filter(|instr| !instr.is_item()) {
+            instr.exec_statement(mem)?;
+        }
+        self.tail.as_ref().map_or(Ok(Value::Unit), |e| e.eval(mem))
+    }
+}
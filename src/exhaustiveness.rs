@@ -0,0 +1,366 @@
+// VÉRIFICATION STATIQUE DES `match`
+// algorithme d'utilité de Maranget (celui de rustc) : un motif est utile s'il filtre
+// une valeur qu'aucune ligne précédente ne filtre. Un bras inutile est inaccessible,
+// et un `_` encore utile après tous les bras donne les motifs non couverts.
+
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+use crate::error::EvalError;
+use crate::identifier::Identifier;
+use crate::items::Items;
+use crate::memory::Memory;
+use crate::parsing::expression::{Expression, MatchArm};
+use crate::parsing::parsedvalue::ParsedValue;
+use crate::parsing::pattern::Pattern;
+use crate::parsing::structure::{Enum, Fields, Struct};
+use crate::r#type::{IntType, Type};
+
+/// an arm that no value can reach, reported as a warning
+pub struct UnreachableArm {
+    pub scrutinee: Expression,
+    pub pattern: Pattern,
+}
+
+impl Display for UnreachableArm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unreachable arm `{}` in `match {}`, earlier arms cover every value it matches", self.pattern, self.scrutinee)
+    }
+}
+
+/// constructor at the head of a pattern, its fields are the sub-patterns
+#[derive(Debug, Clone)]
+enum Ctor {
+    Bool(bool),
+    /// entiers du type donné, bornes incluses, en i128 pour calculer `u64::MAX + 1` sans débordement
+    Int(IntType, i128, i128),
+    Variant(Rc<Enum>, usize),
+    /// the only constructor of a tuple, `()` included
+    Tuple(usize),
+    Struct(Rc<Struct>),
+    /// a character or a string: infinitely many values, only `_` covers them all
+    Opaque(String),
+}
+
+#[derive(Debug, Clone)]
+enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+impl Ctor {
+    fn arity(&self) -> usize {
+        match self {
+            Ctor::Variant(def, i) => match &def.variants[*i].fields {
+                Fields::Unit => 0,
+                Fields::Tuple(ts) => ts.len(),
+                Fields::Struct(fs) => fs.len(),
+            },
+            Ctor::Tuple(n) => *n,
+            Ctor::Struct(def) => def.fields.len(),
+            Ctor::Bool(_) | Ctor::Int(..) | Ctor::Opaque(_) => 0,
+        }
+    }
+
+    /// true if every value built by `other` is built by `self`,
+    /// les intervalles de `other` ont déjà été découpés
+    fn covers(&self, other: &Ctor) -> bool {
+        match (self, other) {
+            (Ctor::Bool(a), Ctor::Bool(b)) => a == b,
+            (Ctor::Int(_, lo, hi), Ctor::Int(_, a, b)) => lo <= a && b <= hi,
+            (Ctor::Variant(_, i), Ctor::Variant(_, j)) => i == j,
+            (Ctor::Tuple(n), Ctor::Tuple(m)) => n == m,
+            (Ctor::Struct(s), Ctor::Struct(t)) => s.name == t.name,
+            (Ctor::Opaque(a), Ctor::Opaque(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+fn show_int(i: i128, t: IntType) -> String {
+    match i {
+        i if i == t.max() => format!("{}::MAX", t),
+        i if i == t.min() && t.is_signed() => format!("{}::MIN", t),
+        i => i.to_string(),
+    }
+}
+
+fn join(pats: &[Pat]) -> String {
+    pats.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+impl Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pat::Wild => write!(f, "_"),
+            Pat::Ctor(Ctor::Bool(b), _) => write!(f, "{}", b),
+            Pat::Ctor(Ctor::Int(t, lo, hi), _) if lo == hi => write!(f, "{}", show_int(*lo, *t)),
+            Pat::Ctor(Ctor::Int(t, lo, hi), _) => write!(f, "{}..={}", show_int(*lo, *t), show_int(*hi, *t)),
+            Pat::Ctor(Ctor::Variant(def, i), args) => {
+                let variant = &def.variants[*i];
+                match &variant.fields {
+                    Fields::Unit => write!(f, "{}::{}", def.name, variant.name),
+                    Fields::Tuple(_) => write!(f, "{}::{}({})", def.name, variant.name, join(args)),
+                    Fields::Struct(fs) => write!(f, "{}::{} {{ {} }}", def.name, variant.name,
+                        fs.iter().zip(args).map(|((id, _), p)| format!("{}: {}", id, p)).collect::<Vec<_>>().join(", ")),
+                }
+            },
+            Pat::Ctor(Ctor::Tuple(1), args) => write!(f, "({},)", args[0]),
+            Pat::Ctor(Ctor::Tuple(_), args) => write!(f, "({})", join(args)),
+            Pat::Ctor(Ctor::Struct(def), args) => write!(f, "{} {{ {} }}", def.name,
+                def.fields.iter().zip(args).map(|((id, _), p)| format!("{}: {}", id, p)).collect::<Vec<_>>().join(", ")),
+            Pat::Ctor(Ctor::Opaque(s), _) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// bornes des intervalles élémentaires de `[lo, hi]` : aucun intervalle des `ranges`
+/// ne commence ni ne s'arrête au milieu de l'un d'eux
+fn split(lo: i128, hi: i128, ranges: &[(i128, i128)]) -> Vec<(i128, i128)> {
+    let mut bounds = vec![lo, hi + 1];
+    for (a, b) in ranges {
+        bounds.extend([*a, b + 1].into_iter().filter(|x| lo < *x && *x <= hi));
+    }
+    bounds.sort();
+    bounds.dedup();
+    bounds.windows(2).map(|w| (w[0], w[1] - 1)).collect()
+}
+
+/// `row` restricted to the values built by `ctor`: its head is replaced by its fields,
+/// none if the head is another constructor
+fn specialize(row: &[Pat], ctor: &Ctor, arity: usize) -> Option<Vec<Pat>> {
+    let mut res = match &row[0] {
+        Pat::Wild => vec![Pat::Wild; arity],
+        Pat::Ctor(c, args) if c.covers(ctor) => args.clone(),
+        Pat::Ctor(..) => return None,
+    };
+    res.extend_from_slice(&row[1..]);
+    Some(res)
+}
+
+/// values matched by `v` and by none of the `rows`, as a list of witnesses:
+/// `v` is useful if the list is not empty
+fn useful(rows: &[Vec<Pat>], v: &[Pat]) -> Vec<Vec<Pat>> {
+    if v.is_empty() {
+        return if rows.is_empty() { vec![vec![]] } else { vec![] }
+    }
+    let heads: Vec<&Ctor> = rows.iter().filter_map(|row| match &row[0] { Pat::Ctor(c, _) => Some(c), Pat::Wild => None }).collect();
+    let ranges: Vec<(i128, i128)> = heads.iter().filter_map(|c| match c { Ctor::Int(_, a, b) => Some((*a, *b)), _ => None }).collect();
+    match &v[0] {
+        // un intervalle est découpé selon ceux des lignes, puis chaque morceau est testé
+        Pat::Ctor(Ctor::Int(t, lo, hi), _) => split(*lo, *hi, &ranges).into_iter()
+            .flat_map(|(a, b)| specialized(rows, v, &Ctor::Int(*t, a, b)))
+            .collect(),
+        Pat::Ctor(ctor, _) => specialized(rows, v, ctor),
+        Pat::Wild => {
+            let (all, missing) = constructors(&heads, &ranges);
+            if missing.is_empty() && !all.is_empty() {
+                all.iter().flat_map(|ctor| specialized(rows, v, ctor)).collect()
+            } else {
+                // signature incomplète : seules les lignes commençant par `_` comptent
+                let defaults: Vec<Vec<Pat>> = rows.iter().filter(|row| matches!(row[0], Pat::Wild)).map(|row| row[1..].to_vec()).collect();
+                let heads: Vec<Pat> = if heads.is_empty() || missing.is_empty() { vec![Pat::Wild] } else {
+                    missing.into_iter().map(|c| { let n = c.arity(); Pat::Ctor(c, vec![Pat::Wild; n]) }).collect()
+                };
+                useful(&defaults, &v[1..]).into_iter()
+                    .flat_map(|w| heads.iter().map(move |h| std::iter::once(h.clone()).chain(w.iter().cloned()).collect()))
+                    .collect()
+            }
+        },
+    }
+}
+
+/// witnesses of `v` whose head is built by `ctor`
+fn specialized(rows: &[Vec<Pat>], v: &[Pat], ctor: &Ctor) -> Vec<Vec<Pat>> {
+    let arity = ctor.arity();
+    let rows: Vec<Vec<Pat>> = rows.iter().filter_map(|row| specialize(row, ctor, arity)).collect();
+    let v = specialize(v, ctor, arity).unwrap();
+    useful(&rows, &v).into_iter().map(|mut w| {
+        let rest = w.split_off(arity);
+        let mut res = vec![Pat::Ctor(ctor.clone(), w)];
+        res.extend(rest);
+        res
+    }).collect()
+}
+
+/// every constructor of the type of the column, and those that no head uses;
+/// le type est celui du premier constructeur, un type infini n'a aucun constructeur
+fn constructors(heads: &[&Ctor], ranges: &[(i128, i128)]) -> (Vec<Ctor>, Vec<Ctor>) {
+    let all = match heads.first() {
+        None | Some(Ctor::Opaque(_)) => return (vec![], vec![]),
+        Some(Ctor::Bool(_)) => vec![Ctor::Bool(false), Ctor::Bool(true)],
+        Some(Ctor::Int(t, ..)) => split(t.min(), t.max(), ranges).into_iter().map(|(a, b)| Ctor::Int(*t, a, b)).collect(),
+        Some(Ctor::Variant(def, _)) => (0..def.variants.len()).map(|i| Ctor::Variant(def.clone(), i)).collect(),
+        Some(ctor) => vec![(*ctor).clone()],
+    };
+    let mut missing: Vec<Ctor> = all.iter().filter(|c| !heads.iter().any(|h| h.covers(c))).cloned().collect();
+    // les intervalles manquants contigus sont regroupés : `3..=isize::MAX`
+    missing.dedup_by(|next, prev| match (&*prev, &*next) {
+        (&Ctor::Int(t, lo, hi), &Ctor::Int(_, start, end)) if hi + 1 == start => { *prev = Ctor::Int(t, lo, end); true },
+        _ => false,
+    });
+    (all, missing)
+}
+
+/// enums and structs known to the checker, those of the block of the `match`
+struct Checker<'a> {
+    mem: &'a Memory,
+    items: &'a Items,
+}
+
+impl Checker<'_> {
+    fn find_enum(&self, id: &Identifier) -> Option<Rc<Enum>> {
+        self.items.find_enum(id).or_else(|| self.mem.find_enum(id).ok())
+    }
+
+    fn find_struct(&self, id: &Identifier) -> Option<Rc<Struct>> {
+        self.items.find_struct(id)
+    }
+
+    /// the pattern, matched against a value of type `t` if known, with its fields in
+    /// declaration order, none if it names an unknown type or does not fit it: the error
+    /// is then left to the evaluation
+    fn lower(&self, p: &Pattern, t: Option<&Type>) -> Option<Pat> {
+        // un entier a le type de la valeur filtrée, à défaut celui de son suffixe
+        let int = |suffix: Option<IntType>| match t {
+            Some(Type::Int(t)) => *t,
+            _ => suffix.unwrap_or_default(),
+        };
+        Some(match p {
+            Pattern::Identifier { .. } | Pattern::Wildcard => Pat::Wild,
+            Pattern::Literal(ParsedValue::Integer(i, suffix)) => Pat::Ctor(Ctor::Int(int(*suffix), *i, *i), vec![]),
+            Pattern::Literal(ParsedValue::Boolean(b)) => Pat::Ctor(Ctor::Bool(*b), vec![]),
+            Pattern::Literal(ParsedValue::Unit) => Pat::Ctor(Ctor::Tuple(0), vec![]),
+            Pattern::Literal(v) => Pat::Ctor(Ctor::Opaque(v.to_string()), vec![]),
+            Pattern::Range { start: ParsedValue::Integer(a, s1), end: ParsedValue::Integer(b, s2), inclusive } =>
+                Pat::Ctor(Ctor::Int(int(s1.or(*s2)), *a, *b - if *inclusive { 0 } else { 1 }), vec![]),
+            Pattern::Range { .. } => Pat::Ctor(Ctor::Opaque(p.to_string()), vec![]),
+            Pattern::Tuple(ps) => {
+                let ts: Vec<Option<&Type>> = match t {
+                    Some(Type::Tuple(ts)) if ts.len() == ps.len() => ts.iter().map(Some).collect(),
+                    _ => vec![None; ps.len()],
+                };
+                Pat::Ctor(Ctor::Tuple(ps.len()), ps.iter().zip(ts).map(|(p, t)| self.lower(p, t)).collect::<Option<_>>()?)
+            },
+            Pattern::Struct { name, fields, .. } => {
+                let def = self.find_struct(name)?;
+                let args = self.lower_fields(&def.fields, fields)?;
+                Pat::Ctor(Ctor::Struct(def), args)
+            },
+            Pattern::Variant(name, variant, fields) => {
+                let def = self.find_enum(name)?;
+                let i = def.variants.iter().position(|v| v.name == *variant)?;
+                let args = match (&def.variants[i].fields, fields.as_deref()) {
+                    (Fields::Unit, None) => vec![],
+                    (Fields::Tuple(ts), Some(Pattern::Tuple(ps))) if ts.len() == ps.len() =>
+                        ps.iter().zip(ts).map(|(p, t)| self.lower(p, Some(t))).collect::<Option<_>>()?,
+                    (Fields::Struct(fs), Some(Pattern::Struct { fields, .. })) => self.lower_fields(fs, fields)?,
+                    _ => return None,
+                };
+                Pat::Ctor(Ctor::Variant(def, i), args)
+            },
+        })
+    }
+
+    /// named field patterns in the order of `defs`, `_` for the fields left out with `..`
+    fn lower_fields(&self, defs: &[(Identifier, Type)], fields: &[(Identifier, Pattern)]) -> Option<Vec<Pat>> {
+        if fields.iter().any(|(id, _)| !defs.iter().any(|(f, _)| f == id)) { return None }
+        defs.iter().map(|(id, t)| match fields.iter().find(|(f, _)| f == id) {
+            Some((_, p)) => self.lower(p, Some(t)),
+            None => Some(Pat::Wild),
+        }).collect()
+    }
+
+    fn check_match(&self, e: &Expression, arms: &[MatchArm], t: Option<&Type>) -> Result<Vec<UnreachableArm>, EvalError> {
+        let Some(pats) = arms.iter().map(|arm| self.lower(&arm.pattern, t)).collect::<Option<Vec<_>>>() else { return Ok(vec![]) };
+        let mut warnings = vec![];
+        let mut rows: Vec<Vec<Pat>> = vec![];
+        for (arm, p) in arms.iter().zip(pats) {
+            if useful(&rows, std::slice::from_ref(&p)).is_empty() {
+                warnings.push(UnreachableArm { scrutinee: e.clone(), pattern: arm.pattern.clone() });
+            }
+            // un bras gardé peut échouer : il ne couvre rien
+            if arm.guard.is_none() { rows.push(vec![p]) }
+        }
+        let missing: Vec<String> = useful(&rows, &[Pat::Wild]).iter().map(|w| w[0].to_string()).collect();
+        if missing.is_empty() { Ok(warnings) } else {
+            Err(EvalError::NonExhaustiveMatch { expression: e.clone(), missing })
+        }
+    }
+}
+
+/// checks a `match` on `scrutinee`, a value of type `t` if known, where `items` are visible:
+/// a value that no arm matches is an error, an arm that can never match is returned as a warning
+pub fn check_match(mem: &Memory, items: &Items, scrutinee: &Expression, arms: &[MatchArm], t: Option<&Type>) -> Result<Vec<UnreachableArm>, EvalError> {
+    Checker { mem, items }.check_match(scrutinee, arms, t)
+}
+
+#[cfg(test)]
+mod test_exhaustiveness {
+    use super::*;
+
+    const MIN: i128 = isize::MIN as i128;
+    const MAX: i128 = isize::MAX as i128;
+
+    fn int(lo: i128, hi: i128) -> Pat {
+        Pat::Ctor(Ctor::Int(IntType::Isize, lo, hi), vec![])
+    }
+
+    fn some(p: Pat) -> Pat {
+        Pat::Ctor(Ctor::Variant(Rc::new(Enum::option()), 1), vec![p])
+    }
+
+    /// patterns missing after arms matching `pats`, as `check_match` reports them
+    fn missing(pats: &[Pat]) -> Vec<String> {
+        let rows: Vec<Vec<Pat>> = pats.iter().map(|p| vec![p.clone()]).collect();
+        useful(&rows, &[Pat::Wild]).iter().map(|w| w[0].to_string()).collect()
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(0, 10, &[]), vec![(0, 10)]);
+        assert_eq!(split(0, 10, &[(3, 5)]), vec![(0, 2), (3, 5), (6, 10)]);
+        assert_eq!(split(0, 10, &[(3, 5), (4, 12)]), vec![(0, 2), (3, 3), (4, 5), (6, 10)]);
+        assert_eq!(split(0, 10, &[(-5, -1), (11, 20)]), vec![(0, 10)]);
+        assert_eq!(split(MIN, MAX, &[(MIN, 0)]), vec![(MIN, 0), (1, MAX)]);
+    }
+
+    #[test]
+    fn test_int_witnesses() {
+        assert_eq!(missing(&[int(0, 0)]), vec!["isize::MIN..=-1", "1..=isize::MAX"]);
+        assert_eq!(missing(&[int(MIN, 2), int(5, MAX)]), vec!["3..=4"]);
+        assert_eq!(missing(&[int(MIN, 2), int(3, 3), int(4, 4)]), vec!["5..=isize::MAX"]);
+        assert!(missing(&[int(MIN, 0), int(1, MAX)]).is_empty());
+        assert!(missing(&[int(0, 0), Pat::Wild]).is_empty());
+    }
+
+    #[test]
+    fn test_int_types() {
+        let int = |t, lo, hi| Pat::Ctor(Ctor::Int(t, lo, hi), vec![]);
+        assert!(missing(&[int(IntType::U8, 0, 255)]).is_empty());
+        assert_eq!(missing(&[int(IntType::U8, 1, 254)]), vec!["0", "u8::MAX"]);
+        assert_eq!(missing(&[int(IntType::I8, 0, 127)]), vec!["i8::MIN..=-1"]);
+        let max = IntType::U64.max();
+        assert!(missing(&[int(IntType::U64, 0, MAX), int(IntType::U64, MAX + 1, max)]).is_empty());
+        assert_eq!(missing(&[int(IntType::U64, 0, MAX)]), vec!["9223372036854775808..=u64::MAX"]);
+    }
+
+    #[test]
+    fn test_ctor_witnesses() {
+        let pair = |a, b| Pat::Ctor(Ctor::Tuple(2), vec![a, b]);
+        let bool = |b| Pat::Ctor(Ctor::Bool(b), vec![]);
+        assert_eq!(missing(&[pair(bool(true), Pat::Wild), pair(Pat::Wild, bool(true))]), vec!["(false, false)"]);
+        assert_eq!(missing(&[some(Pat::Wild)]), vec!["Option::None"]);
+        let none = Pat::Ctor(Ctor::Variant(Rc::new(Enum::option()), 0), vec![]);
+        assert_eq!(missing(&[some(int(0, MAX))]), vec!["Option::None"]);
+        assert_eq!(missing(&[some(int(0, MAX)), none]), vec!["Option::Some(isize::MIN..=-1)"]);
+        assert_eq!(missing(&[Pat::Ctor(Ctor::Opaque("'a'".to_string()), vec![])]), vec!["_"]);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        assert!(useful(&[vec![int(0, 10)]], &[int(3, 5)]).is_empty());
+        assert!(!useful(&[vec![int(0, 10)]], &[int(5, 12)]).is_empty());
+        assert!(useful(&[vec![Pat::Wild]], &[some(Pat::Wild)]).is_empty());
+    }
+}
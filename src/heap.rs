@@ -2,8 +2,9 @@ use crate::memory::Address;
 use crate::memorycell::MemoryCell;
 use crate::value::Value;
 use crate::error::EvalError;
+use std::fmt::Display;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Heap(Vec<MemoryCell>);
 
 impl Heap {
@@ -21,19 +22,48 @@ impl Heap {
             }
         }
         self.0.push(MemoryCell::new_uninitialized());
-        return Address::HeapAddress(self.0.len() - 1);
+        Address::HeapAddress(self.0.len() - 1)
     }
 
-    pub fn free(&mut self, a: usize) { self.0[a] = MemoryCell::NotAllocated }
+    /// allocates `len` contiguous cells, returns the index of the first one
+    pub fn malloc_block(&mut self, len: usize) -> usize {
+        // le premier trou assez grand, sinon le bloc finit à la fin du tas
+        let mut start = 0;
+        for addr in 0..self.0.len() {
+            if self.0[addr].is_allocated() { start = addr + 1 }
+            else if addr + 1 - start == len { break }
+        }
+        if self.0.len() < start + len { self.0.resize(start + len, MemoryCell::NotAllocated) }
+        for cell in &mut self.0[start..start + len] { *cell = MemoryCell::new_uninitialized() }
+        start
+    }
 
-    pub fn get(&self, index: usize) -> Result<Value, EvalError> { 
-        if self.0.len() <= index { return Err(EvalError::NonAllocatedCell(None)) }
-        if !self.0[index].is_allocated() { return Err(EvalError::NonAllocatedCell(None)) }
-        self.0[index].get_value()
+    pub fn free(&mut self, a: usize) { self.0[a] = MemoryCell::Freed }
+
+    /// moves the content of the cell `from` to the cell `to`, `from` is left uninitialized
+    pub fn move_cell(&mut self, from: usize, to: usize) {
+        self.0[to] = std::mem::replace(&mut self.0[from], MemoryCell::new_uninitialized());
+    }
+
+    pub fn cell(&self, index: usize) -> Result<&MemoryCell, EvalError> {
+        self.0.get(index).ok_or(EvalError::NonAllocatedCell(None))
+    }
+
+    pub fn cell_mut(&mut self, index: usize) -> Result<&mut MemoryCell, EvalError> {
+        self.0.get_mut(index).ok_or(EvalError::NonAllocatedCell(None))
     }
 
     pub fn set(&mut self, index: usize, value: Value) -> Result<(), EvalError> {
         if self.0.len() <= index { return Err(EvalError::NonAllocatedCell(None)) }
         self.0[index].set_value(value)
     }
-}
\ No newline at end of file
+}
+
+impl Display for Heap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (addr, cell) in self.0.iter().enumerate() {
+            writeln!(f, "{} : {}", Address::HeapAddress(addr), cell)?;
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use std::{fmt::{Display, Debug}, rc::Rc};
 
 ////////////////////////////////////////////////////////////////////////////
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Identifier (Rc<str>);
 ////////////////////////////////////////////////////////////////////////////
 
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::{error::EvalError, identifier::Identifier, parsing::function::Function};
+use crate::parsing::instruction::Instruction;
+use crate::parsing::structure::{Enum, Struct};
+
+/// items declared in a block, visible in the whole block and in the blocks it encloses
+#[derive(Debug, Clone, Default)]
+pub struct Items {
+    /// items of the enclosing block
+    parent: Option<Rc<Items>>,
+    functions: HashMap<Identifier, Rc<Function>>,
+    structs: HashMap<Identifier, Rc<Struct>>,
+    enums: HashMap<Identifier, Rc<Enum>>,
+}
+
+impl Items {
+    pub fn new() -> Self {
+        Items::default()
+    }
+
+    /// scope of the items of a block nested in the one of `parent`
+    pub fn nested(parent: Rc<Items>) -> Self {
+        Items { parent: Some(parent), ..Items::default() }
+    }
+
+    /// scope of the items declared by `instrs`, the statements of a block nested in the one
+    /// of `parent`, as the checkers see it: a name defined twice is left to the evaluation
+    pub fn block(parent: &Rc<Items>, instrs: &[Instruction]) -> Rc<Items> {
+        if !instrs.iter().any(Instruction::is_item) { return parent.clone() }
+        let mut items = Items::nested(parent.clone());
+        for instr in instrs {
+            let _ = match instr {
+                Instruction::FnDef(fun) => items.declare_function(fun),
+                Instruction::StructDef(def) => items.declare_struct(def),
+                Instruction::EnumDef(def) => items.declare_enum(def),
+                _ => Ok(()),
+            };
+        }
+        Rc::new(items)
+    }
+
+    /// an item shadows the items of the same name of the enclosing blocks
+    pub fn declare_function(&mut self, fun: &Rc<Function>) -> Result<(), EvalError> {
+        if self.functions.contains_key(&fun.name) { return Err(EvalError::AlreadyDefined(fun.name.clone())) }
+        self.functions.insert(fun.name.clone(), fun.clone());
+        Ok(())
+    }
+
+    /// the function `id` and the scope of its definition, where its body sees the items
+    pub fn find_function(self: &Rc<Self>, id: &Identifier) -> Option<(Rc<Function>, Rc<Items>)> {
+        let mut scope = self;
+        loop {
+            if let Some(fun) = scope.functions.get(id) { return Some((fun.clone(), scope.clone())) }
+            scope = scope.parent.as_ref()?;
+        }
+    }
+
+    /// structs and enums share the names of types
+    pub fn declare_struct(&mut self, def: &Rc<Struct>) -> Result<(), EvalError> {
+        if self.structs.contains_key(&def.name) || self.enums.contains_key(&def.name) { return Err(EvalError::AlreadyDefined(def.name.clone())) }
+        self.structs.insert(def.name.clone(), def.clone());
+        Ok(())
+    }
+
+    pub fn find_struct(&self, id: &Identifier) -> Option<Rc<Struct>> {
+        self.structs.get(id).cloned().or_else(|| self.parent.as_ref()?.find_struct(id))
+    }
+
+    pub fn declare_enum(&mut self, def: &Rc<Enum>) -> Result<(), EvalError> {
+        if self.structs.contains_key(&def.name) || self.enums.contains_key(&def.name) { return Err(EvalError::AlreadyDefined(def.name.clone())) }
+        self.enums.insert(def.name.clone(), def.clone());
+        Ok(())
+    }
+
+    pub fn find_enum(&self, id: &Identifier) -> Option<Rc<Enum>> {
+        self.enums.get(id).cloned().or_else(|| self.parent.as_ref()?.find_enum(id))
+    }
+
+    /// names of the items visible from this scope
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        let mut ids: Vec<Identifier> = self.functions.keys().chain(self.structs.keys()).chain(self.enums.keys()).cloned().collect();
+        if let Some(parent) = &self.parent { ids.extend(parent.identifiers()) }
+        ids
+    }
+}
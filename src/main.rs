@@ -10,60 +10,90 @@ mod eval;
 mod r#type;
 mod memorycell;
 mod memory;
+mod items;
 mod heap;
 mod pointer;
+mod controlflow;
+mod range;
+mod exhaustiveness;
+mod typecheck;
+mod repl;
 
 
 // LISTE DES IMPORTS
-use std::io::{self, BufRead, Write};
-use identifier::Identifier;
-use parsing::instruction::Instruction;
+use parsing::program::Program;
 use namespace::NameSpace;
 use value::Value;
+use repl::repl;
 use crate::parser::Parse;
 use crate::error::Error;
-use crate::memory::Memory;
-use crate::r#type::Type;
+use crate::memory::{Memory, DEFAULT_MAX_DEPTH, set_stack_size};
 
-// AFFICHAGE DU PROMPT
-fn prompt() {
-    print!("µRust # ");
-    io::stdout().flush().unwrap();
-}
-
-
-// fn parse_eval(input: &str, ns: &NameSpace) -> Result<isize, Error> {
-//     match Expression::parse(input) {
-//         Ok(expr) => Ok(expr.eval(ns)?),
-//         Err(e) => Err(Error::ParseError(e)),
-//     }
-// }
-
-fn parse_exec(input: &str, nss: &mut Memory) -> Result<(Option<Identifier>, Value), Error> {
-    match Instruction::parse(input) {
-        Ok(instr) => {
-            instr.exec(nss).map_err(|err| Error::EvalError(err))
-        }
+fn parse_run(input: &str, mem: &mut Memory) -> Result<Value, Error> {
+    match Program::parse(input) {
+        Ok(program) => {
+            for warning in program.check_types(mem)? { eprintln!("warning: {}", warning) }
+            program.exec(mem).map_err(|cf| Error::EvalError(cf.into()))
+        },
         Err(e) => Err(Error::ParseError(e)),
     }
 }
 
+// l'interpréteur est récursif : on lui donne une pile assez grande pour
+// atteindre la profondeur d'appel maximale de µRust sans déborder la pile hôte.
+// Un appel µRust récursif à travers un `if`, un `match` ou un `for` utilise
+// jusqu'à 50 Kio de pile hôte en release et 440 Kio en debug (680 Kio avec des blocs
+// très imbriqués) ; au-delà, `Memory::check_stack` arrête l'évaluation avant le débordement.
+// Avec `--max-depth 1000`, le thread réserve 64 Mio + 1000 × 768 Kio ≈ 832 Mio d'espace
+// d'adressage en debug (128 Mio en release) : seules les pages touchées par la récursion
+// sont allouées, mais une limite `ulimit -v` plus basse empêche le lancement
+const BASE_STACK_SIZE: usize = 64 << 20;
+const STACK_SIZE_PER_CALL: usize = if cfg!(debug_assertions) { 768 << 10 } else { 64 << 10 };
+
 // FONCTION PRINCIPALE
 fn main(){
-    prompt();
-    let mut nss = Memory::new();
-    nss.push(NameSpace::new());
-    let stdin = io::stdin().lock();
-    for line in stdin.lines() {
-        let line = line.unwrap();
-        match parse_exec(&line, &mut nss) {
-            Ok((id, val)) => {
-                println!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val);
-            }
-            Err(e) => {
-                println!("{}", e);
-            }
+    let mut max_depth = DEFAULT_MAX_DEPTH;
+    let mut file = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-depth" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => max_depth = n,
+                None => { eprintln!("--max-depth expects a number"); std::process::exit(2) }
+            },
+            "run" if file.is_none() => match args.next() {
+                Some(path) => file = Some(path),
+                None => { eprintln!("run expects a file"); std::process::exit(2) }
+            },
+            _ => { eprintln!("unknown argument `{}`", arg); std::process::exit(2) }
         }
-        prompt();
+    }
+    let stack_size = BASE_STACK_SIZE.saturating_add(max_depth.saturating_mul(STACK_SIZE_PER_CALL));
+    let interpreter = std::thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(move || {
+            set_stack_size(stack_size);
+            match file {
+                Some(path) => run(&path, max_depth),
+                None => { repl(max_depth); 0 }
+            }
+        })
+        .unwrap_or_else(|e| { eprintln!("cannot reserve a stack for --max-depth {}: {}", max_depth, e); std::process::exit(2) });
+    std::process::exit(interpreter.join().unwrap());
+}
+
+// EXÉCUTION D'UN FICHIER, renvoie le code de sortie
+fn run(path: &str, max_depth: usize) -> i32 {
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(e) => { eprintln!("cannot read `{}`: {}", path, e); return 2 }
+    };
+    let mut mem = Memory::new();
+    mem.set_max_depth(max_depth);
+    mem.push(NameSpace::new());
+    match parse_run(&input, &mut mem) {
+        Ok(Value::Unit) => 0,
+        Ok(val) => { println!("{}", mem.show(&val)); 0 }
+        Err(e) => { eprintln!("{}", e); 1 }
     }
 }
@@ -1,15 +1,36 @@
+use std::cell::Cell;
 use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 use crate::error::EvalError;
 use crate::identifier::Identifier;
 use crate::namespacestack::NameSpaceStack;
 use crate::heap::Heap;
 use crate::namespace::NameSpace;
-use crate::value::Value;
+use crate::parsing::function::Function;
+use crate::parsing::structure::{Struct, Enum};
+use crate::value::{Value, Shape};
+use crate::r#type::Type;
+use crate::pointer::Pointer;
+use crate::memorycell::MemoryCell;
+use crate::items::Items;
 
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Address {
     StackAddress(usize, Identifier),
     HeapAddress(usize),
+    /// `index`-th component of the tuple, array or struct at an address
+    FieldAddress(Box<Address>, usize),
+}
+
+impl Address {
+    /// index of a heap cell, panics on a stack address
+    pub fn heap_index(&self) -> usize {
+        match self {
+            Address::HeapAddress(n) => *n,
+            Address::StackAddress(..) | Address::FieldAddress(..) => unreachable!("{} is not a heap address", self),
+        }
+    }
 }
 
 impl Display for Address {
@@ -17,60 +38,442 @@ impl Display for Address {
         match self {
             Address::StackAddress(num, var) => write!(f, "@[{},{}]", num, var),
             Address::HeapAddress(num) => write!(f, "@{}", num),
+            Address::FieldAddress(addr, index) => write!(f, "{}.{}", addr, index),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Memory {
     stack: NameSpaceStack,
     heap: Heap,
+    /// functions of the current block and of the enclosing ones
+    items: Rc<Items>,
+    /// items of the callers, restored at the end of each call
+    callers: Vec<Rc<Items>>,
+    max_depth: usize,
+}
+
+/// capacité d'un `Vec` à sa première allocation, comme en Rust
+const MIN_VEC_CAPACITY: usize = 4;
+
+/// nombre maximal d'appels imbriqués par défaut
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// pile hôte gardée en réserve pour remonter une erreur une fois la limite atteinte
+const STACK_MARGIN: usize = 1 << 20;
+
+thread_local! {
+    /// lowest address of the host stack the interpreter may reach, 0 if it is not known
+    static STACK_LIMIT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// approximate address of the top of the host stack
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// the interpreter runs on `size` bytes of host stack from here,
+/// to be called at the start of the thread running it
+pub fn set_stack_size(size: usize) {
+    STACK_LIMIT.with(|limit| limit.set(stack_pointer().saturating_sub(size.saturating_sub(STACK_MARGIN))));
 }
 
 impl Memory {
-    pub fn new() -> Self { Memory { stack: NameSpaceStack::new(), heap: Heap::new() } }
+    pub fn new() -> Self { Memory { stack: NameSpaceStack::new(), heap: Heap::new(), items: Rc::new(Items::new()), callers: vec![], max_depth: DEFAULT_MAX_DEPTH } }
 
-    pub fn pop(&mut self) -> Option<NameSpace> { self.stack.pop() }
+    /// pops a namespace, dropping the owned values of its variables
+    pub fn pop(&mut self) -> Option<NameSpace> {
+        let ns = self.stack.pop()?;
+        ns.owned_values().iter().for_each(|v| self.drop_value(v));
+        Some(ns)
+    }
 
     pub fn push(&mut self, ns: NameSpace) { self.stack.push(ns) }
 
+    pub fn set_max_depth(&mut self, max_depth: usize) { self.max_depth = max_depth }
+
+    pub fn stack(&self) -> &NameSpaceStack { &self.stack }
+
+    pub fn heap(&self) -> &Heap { &self.heap }
+
+    /// variables visible from the current scope, functions, structs and enums, sorted
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        let mut ids = self.stack.identifiers();
+        ids.extend(self.items.identifiers());
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// the body of `name` sees the items of the block defining it, not those of the caller
+    pub fn push_frame(&mut self, name: &Identifier, ns: NameSpace) -> Result<(), EvalError> {
+        if self.stack.depth() >= self.max_depth {
+            let mut calls = self.stack.calls();
+            calls.push(name.clone());
+            return Err(EvalError::StackOverflow { depth: self.max_depth, calls })
+        }
+        let (_, scope) = self.items.find_function(name).ok_or(EvalError::Undefined(name.clone()))?;
+        self.callers.push(std::mem::replace(&mut self.items, scope));
+        self.stack.push_frame(name, ns);
+        Ok(())
+    }
+
+    /// a `StackOverflow` before the host stack runs out, whatever the call depth:
+    /// nested expressions also use host stack, more so in a debug build
+    pub fn check_stack(&self) -> Result<(), EvalError> {
+        if stack_pointer() < STACK_LIMIT.with(Cell::get) {
+            return Err(EvalError::StackOverflow { depth: self.stack.depth(), calls: self.stack.calls() })
+        }
+        Ok(())
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.items = self.callers.pop().unwrap();
+        for ns in self.stack.pop_frame() {
+            ns.owned_values().iter().for_each(|v| self.drop_value(v));
+        }
+    }
+
     pub fn declare(&mut self, id: &Identifier, mutable: bool, value: Value) -> Result<(), EvalError> { self.stack.declare(id, mutable, value) }
 
-    pub fn write_var(&mut self, id: &Identifier, value: &Value) -> Result<(), EvalError>{ self.stack.set(id, value) }
+    /// the previous owned value of the variable is dropped
+    pub fn write_var(&mut self, id: &Identifier, value: &Value) -> Result<(), EvalError>{
+        let old = self.get_address(id).and_then(|addr| self.owned_values_at(&addr)).unwrap_or_default();
+        self.stack.set(id, value)?;
+        self.drop_replaced(old, value);
+        Ok(())
+    }
 
     pub fn get_address(&self, id: &Identifier) -> Result<Address, EvalError> { self.stack.get_address(id) }
 
     pub fn find(&self, id: &Identifier) -> Result<Value, EvalError> { self.stack.find(id) }
 
+    /// reads a variable, moving its value out if it is owned
+    pub fn take(&mut self, id: &Identifier) -> Result<Value, EvalError> { self.stack.take(id) }
+
+    /// items visible from the current scope
+    pub fn items(&self) -> &Rc<Items> { &self.items }
+
+    /// enters a block declaring items, returns the scope to restore when leaving it
+    pub fn push_items(&mut self) -> Rc<Items> {
+        let outer = self.items.clone();
+        self.items = Rc::new(Items::nested(outer.clone()));
+        outer
+    }
+
+    pub fn pop_items(&mut self, outer: Rc<Items>) { self.items = outer }
+
+    pub fn declare_function(&mut self, fun: &Rc<Function>) -> Result<(), EvalError> {
+        Rc::make_mut(&mut self.items).declare_function(fun)
+    }
+
+    pub fn find_function(&self, id: &Identifier) -> Result<Rc<Function>, EvalError> {
+        self.items.find_function(id).map(|(fun, _)| fun).ok_or(EvalError::Undefined(id.clone()))
+    }
+
+    pub fn declare_struct(&mut self, def: &Rc<Struct>) -> Result<(), EvalError> {
+        Rc::make_mut(&mut self.items).declare_struct(def)
+    }
+
+    pub fn find_struct(&self, id: &Identifier) -> Result<Rc<Struct>, EvalError> {
+        self.items.find_struct(id).ok_or(EvalError::Undefined(id.clone()))
+    }
+
+    pub fn declare_enum(&mut self, def: &Rc<Enum>) -> Result<(), EvalError> {
+        Rc::make_mut(&mut self.items).declare_enum(def)
+    }
+
+    /// `Option` is predefined, unless the program defines it
+    pub fn find_enum(&self, id: &Identifier) -> Result<Rc<Enum>, EvalError> {
+        self.items.find_enum(id)
+            .or_else(|| (id.to_string() == "Option").then(|| Rc::new(Enum::option())))
+            .ok_or(EvalError::Undefined(id.clone()))
+    }
+
+    /// pops a namespace whose values are still owned elsewhere, nothing is dropped
+    pub fn discard(&mut self) -> Option<NameSpace> { self.stack.pop() }
+
     pub fn malloc(&mut self) -> Address { self.heap.malloc() }
 
 
-    pub fn value_at(&self, addr: &Address) -> Result<Value, EvalError> { 
+    fn cell(&self, addr: &Address) -> Result<&MemoryCell, EvalError> {
         match addr {
-            Address::HeapAddress(n) => self.heap.get(*n),
-            _ => todo!()
+            Address::HeapAddress(n) => self.heap.cell(*n),
+            Address::StackAddress(n, id) => self.stack.cell_at(*n, id),
+            Address::FieldAddress(addr, index) => self.cell(addr)?.field(*index).ok_or(EvalError::NonAllocatedCell(None)),
         }
     }
 
-    pub fn write_at(&mut self, addr: &Address, v: Value) -> Result<(), EvalError> {
+    fn cell_mut(&mut self, addr: &Address) -> Result<&mut MemoryCell, EvalError> {
         match addr {
-            Address::HeapAddress(n) => self.heap.set(*n, v),
-            _ => todo!()
+            Address::HeapAddress(n) => self.heap.cell_mut(*n),
+            Address::StackAddress(n, id) => self.stack.cell_at_mut(*n, id),
+            Address::FieldAddress(addr, index) => self.cell_mut(addr)?.field_mut(*index).ok_or(EvalError::NonAllocatedCell(None)),
         }
     }
 
+    /// address of the component `field` of the tuple or struct at `addr`, if it has one
+    pub fn field_address(&self, addr: &Address, field: &Identifier) -> Option<Address> {
+        self.cell(addr).ok()?.field_index(field).map(|index| Address::FieldAddress(Box::new(addr.clone()), index))
+    }
+
+    /// number of elements of the array at `addr`, none if it is not an array
+    pub fn array_len(&self, addr: &Address) -> Option<usize> {
+        self.cell(addr).ok()?.len()
+    }
+
+    /// address of the `index`-th element of the array at `addr`, which must be in bounds:
+    /// the elements of an array in the heap are contiguous heap cells
+    pub fn element_address(&self, addr: &Address, index: usize) -> Address {
+        match self.cell(addr) {
+            Ok(MemoryCell::HeapArray { start, .. }) => Address::HeapAddress(start + index),
+            _ => Address::FieldAddress(Box::new(addr.clone()), index),
+        }
+    }
+
+    /// `(start, len, cap)` of the block of the array at `addr`, if it is in the heap
+    fn heap_block(&self, addr: &Address) -> Option<(usize, usize, usize)> {
+        match self.cell(addr) {
+            Ok(MemoryCell::HeapArray { start, len, cap }) => Some((*start, *len, *cap)),
+            _ => None,
+        }
+    }
+
+    /// frees the block of the array at `addr`, its elements are not dropped
+    fn free_block(&mut self, addr: &Address) {
+        if let Some((start, _, cap)) = self.heap_block(addr) {
+            (start..start + cap).for_each(|n| self.heap.free(n))
+        }
+    }
+
+    /// type of the values the cell at `addr` may hold, none for a heap cell never written
+    pub fn type_at(&self, addr: &Address) -> Result<Option<Type>, EvalError> {
+        match self.heap_block(addr) {
+            Some((start, len, _)) => Ok((start..start + len).map(|n| self.heap.cell(n).ok()?.r#type()).collect::<Option<_>>().map(|ts| Shape::Array.r#type(ts))),
+            None => Ok(self.cell(addr)?.r#type()),
+        }
+    }
+
+    /// type of `v` as precise as the annotation `t`: the target of a pointer and the elements
+    /// of a `Vec` are read when `t` gives their type, and a generic parameter such as the `T`
+    /// of `Option::Some(T)` is of any type
+    pub fn type_as(&self, v: &Value, t: &Type) -> Type {
+        match (v, t) {
+            (Value::Pointer(p), Type::PointerTo(target)) => match self.value_at(p.get_address()) {
+                Ok(v) => Type::PointerTo(Box::new(self.type_as(&v, target))),
+                // une cible illisible (non allouée, libérée) n'est pas une erreur de type
+                Err(_) => t.clone(),
+            },
+            (Value::Vec(p), Type::VecOf(elem)) => {
+                // les éléments d'un `Vec` sont tous du type du premier, un `Vec` vide est de tout type
+                let first = self.vec_block(p).ok().filter(|(_, len, _)| *len > 0).and_then(|(start, _, _)| self.heap.cell(start).ok()?.get_value().ok());
+                match first {
+                    Some(v) => Type::VecOf(Box::new(self.type_as(&v, elem))),
+                    None => t.clone(),
+                }
+            },
+            (Value::Tuple(vs), Type::Tuple(ts)) if vs.len() == ts.len() => Type::Tuple(vs.iter().zip(ts).map(|(v, t)| self.type_as(v, t)).collect()),
+            (Value::Array(vs), Type::Array(elem, _)) => Type::Array(Box::new(vs.first().map_or(Type::Unit, |v| self.type_as(v, elem))), vs.len()),
+            (_, Type::Named(name)) if self.find_struct(name).is_err() && self.find_enum(name).is_err() => t.clone(),
+            _ => Type::from(v),
+        }
+    }
+
+    pub fn value_at(&self, addr: &Address) -> Result<Value, EvalError> {
+        match self.heap_block(addr) {
+            Some((start, len, _)) => Ok(Value::Array((start..start + len).map(|n| self.heap.cell(n)?.get_value()).collect::<Result<_, _>>()?)),
+            None => self.cell(addr)?.get_value(),
+        }
+    }
+
+    /// reads the value at `addr`, moving it out if it is owned
+    pub fn take_at(&mut self, addr: &Address) -> Result<Value, EvalError> {
+        match self.heap_block(addr) {
+            Some((start, len, _)) => Ok(Value::Array((start..start + len).map(|n| self.heap.cell_mut(n)?.take_value()).collect::<Result<_, _>>()?)),
+            None => self.cell_mut(addr)?.take_value(),
+        }
+    }
+
+    fn owned_values_at(&self, addr: &Address) -> Result<Vec<Value>, EvalError> {
+        match self.heap_block(addr) {
+            Some((start, len, _)) => Ok((start..start + len).flat_map(|n| self.heap.cell(n).map(|mc| mc.owned_values()).unwrap_or_default()).collect()),
+            None => Ok(self.cell(addr)?.owned_values()),
+        }
+    }
+
+    pub fn is_mutable(&self, addr: &Address) -> Result<bool, EvalError> {
+        Ok(self.cell(addr)?.is_mutable())
+    }
+
+    /// the previous owned values at `addr` are dropped; an array written in a heap cell
+    /// gets a block of contiguous cells, reused if it has the same length
+    pub fn write_at(&mut self, addr: &Address, v: Value) -> Result<(), EvalError> {
+        if let Some(t) = self.type_at(addr)?.filter(|t| *t != Type::from(&v)) {
+            return Err(EvalError::TypeMismatch { expression: None, expected: t, found: Some(Type::from(&v)) })
+        }
+        let old = self.owned_values_at(addr)?;
+        match (addr, &v) {
+            (Address::HeapAddress(n), Value::Array(vs)) if self.heap.cell(*n)?.is_allocated() => {
+                let start = match self.heap_block(addr) {
+                    Some((start, len, cap)) if len == vs.len() && cap == len => start,
+                    _ => {
+                        self.free_block(addr);
+                        self.heap.malloc_block(vs.len())
+                    },
+                };
+                for (i, v) in vs.iter().enumerate() {
+                    *self.heap.cell_mut(start + i)? = MemoryCell::new_initialized(true, v.clone());
+                }
+                *self.heap.cell_mut(*n)? = MemoryCell::HeapArray { start, len: vs.len(), cap: vs.len() };
+            },
+            _ => {
+                self.cell_mut(addr)?.set_value(v.clone())?;
+                self.free_block(addr);
+            },
+        }
+        self.drop_replaced(old, &v);
+        Ok(())
+    }
+
+    /// drops the owned values overwritten by `v`, except those `v` still owns (`s = s + t`)
+    fn drop_replaced(&mut self, old: Vec<Value>, v: &Value) {
+        let kept = v.owned_parts();
+        old.iter().filter(|old| !kept.contains(old)).for_each(|old| self.drop_value(old));
+    }
+
+    /// frees the heap memory owned by a value going out of scope
+    pub fn drop_value(&mut self, v: &Value) {
+        match v {
+            Value::String(p) => if let Address::HeapAddress(n) = p.get_address() { self.heap.free(*n) },
+            Value::Vec(p) => self.free_array(p),
+            Value::Tuple(vs) | Value::Array(vs) => vs.iter().for_each(|v| self.drop_value(v)),
+            Value::Struct(_, fields) => fields.iter().for_each(|(_, v)| self.drop_value(v)),
+            Value::Variant(_, _, fields) => self.drop_value(fields),
+            _ => (),
+        }
+    }
+
+    /// allocates the heap cell of a new `String`
+    pub fn new_string(&mut self, text: &str) -> Value {
+        let addr = self.malloc();
+        self.heap.set(addr.heap_index(), Value::Str(Rc::from(text))).unwrap();
+        Value::String(Pointer::new(addr))
+    }
+
+    /// text of a `&str` or of a `String`, `None` for other values
+    pub fn text(&self, v: &Value) -> Result<Option<Rc<str>>, EvalError> {
+        match v {
+            Value::Str(s) => Ok(Some(s.clone())),
+            Value::String(p) => match self.value_at(p.get_address())? {
+                Value::Str(s) => Ok(Some(s)),
+                _ => unreachable!("a String points to its text"),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// replaces the text of a `String` in place
+    pub fn set_text(&mut self, p: &Pointer, text: &str) -> Result<(), EvalError> {
+        self.heap.set(p.get_address().heap_index(), Value::Str(Rc::from(text)))
+    }
+
+    /// value as printed by the REPL, with the text of the `String`s
+    pub fn show(&self, v: &Value) -> String {
+        match v {
+            Value::Tuple(vs) if vs.len() == 1 => format!("({},)", self.show(&vs[0])),
+            Value::Tuple(vs) => format!("({})", vs.iter().map(|v| self.show(v)).collect::<Vec<_>>().join(", ")),
+            Value::Array(vs) => format!("[{}]", vs.iter().map(|v| self.show(v)).collect::<Vec<_>>().join(", ")),
+            Value::Vec(p) => self.value_at(p.get_address()).map_or(v.to_string(), |vs| self.show(&vs)),
+            Value::Struct(name, fields) => format!("{} {{ {} }}", name, fields.iter().map(|(id, v)| format!("{}: {}", id, self.show(v))).collect::<Vec<_>>().join(", ")),
+            Value::Variant(name, variant, fields) => match &**fields {
+                Value::Unit => format!("{}::{}", name, variant),
+                Value::Tuple(vs) => format!("{}::{}({})", name, variant, vs.iter().map(|v| self.show(v)).collect::<Vec<_>>().join(", ")),
+                fields => format!("{}::{}", name, self.show(fields)),
+            },
+            _ => match self.text(v) {
+                Ok(Some(s)) if matches!(v, Value::String(_)) => format!("{:?}", s),
+                _ => v.to_string(),
+            },
+        }
+    }
+
+    /// frees the heap cell at `p`, the block of its elements if it is an array,
+    /// and the owned values they hold
+    fn free_array(&mut self, p: &Pointer) {
+        let addr = p.get_address();
+        self.owned_values_at(addr).unwrap_or_default().iter().for_each(|v| self.drop_value(v));
+        self.free_block(addr);
+        self.heap.free(addr.heap_index());
+    }
+
+    /// allocates a `Vec` holding `vs`, with room for `cap` elements at least
+    pub fn new_vec(&mut self, vs: Vec<Value>, cap: usize) -> Value {
+        let addr = self.malloc();
+        let cap = cap.max(vs.len());
+        let start = self.heap.malloc_block(cap);
+        let len = vs.len();
+        for (i, v) in vs.into_iter().enumerate() {
+            self.heap.set(start + i, v).unwrap();
+        }
+        *self.heap.cell_mut(addr.heap_index()).unwrap() = MemoryCell::HeapArray { start, len, cap };
+        Value::Vec(Pointer::new(addr))
+    }
+
+    /// `(start, len, cap)` of the buffer of the `Vec` at `p`
+    pub fn vec_block(&self, p: &Pointer) -> Result<(usize, usize, usize), EvalError> {
+        match self.heap.cell(p.get_address().heap_index())? {
+            MemoryCell::HeapArray { start, len, cap } => Ok((*start, *len, *cap)),
+            MemoryCell::Freed => Err(EvalError::UseAfterFree(None)),
+            _ => Err(EvalError::NonAllocatedCell(None)),
+        }
+    }
+
+    /// inserts `v` at `index`, which must be at most the length; a full buffer is reallocated:
+    /// a new block twice as large, the elements moved there and the old block freed
+    pub fn vec_insert(&mut self, p: &Pointer, index: usize, v: Value) -> Result<(), EvalError> {
+        let (mut start, len, mut cap) = self.vec_block(p)?;
+        if len == cap {
+            let new_cap = (2 * cap).max(MIN_VEC_CAPACITY);
+            let new_start = self.heap.malloc_block(new_cap);
+            (0..len).for_each(|i| self.heap.move_cell(start + i, new_start + i));
+            (start..start + cap).for_each(|n| self.heap.free(n));
+            (start, cap) = (new_start, new_cap);
+        }
+        (index..len).rev().for_each(|i| self.heap.move_cell(start + i, start + i + 1));
+        self.heap.set(start + index, v)?;
+        *self.heap.cell_mut(p.get_address().heap_index())? = MemoryCell::HeapArray { start, len: len + 1, cap };
+        Ok(())
+    }
+
+    /// removes the element at `index`, which must be less than the length, the next ones are shifted
+    pub fn vec_remove(&mut self, p: &Pointer, index: usize) -> Result<Value, EvalError> {
+        let (start, len, cap) = self.vec_block(p)?;
+        let v = self.heap.cell_mut(start + index)?.take_value()?;
+        (index + 1..len).for_each(|i| self.heap.move_cell(start + i, start + i - 1));
+        *self.heap.cell_mut(start + len - 1)? = MemoryCell::new_uninitialized();
+        *self.heap.cell_mut(p.get_address().heap_index())? = MemoryCell::HeapArray { start, len: len - 1, cap };
+        Ok(v)
+    }
+
     pub fn free(&mut self, add: &Value) -> Result<Value, EvalError>{
         match add {
+            Value::String(_) | Value::Vec(_) => Err(EvalError::CannotFreeOwnedValue(None)),
             Value::Pointer(p) => {
                 match &p.get_address() {
-                    Address::HeapAddress(n) => {
-                        self.heap.free(*n);
+                    Address::HeapAddress(_) => {
+                        // la valeur possédée par la cellule est libérée avec elle,
+                        // un tableau avec le bloc de ses éléments
+                        if let MemoryCell::Freed = self.cell(p.get_address())? { return Err(EvalError::UseAfterFree(None)) }
+                        self.free_array(p);
                         Ok(Value::Unit)
                     }
-                    _ => todo!()
+                    // `&x`, `&t.1`, `&a[i]` : une variable, ou une partie d'une valeur
+                    _ => Err(EvalError::CannotFreeNonHeapPointer(None)),
                 }
             },
-            _ => todo!()
+            v => Err(EvalError::TypeMismatch { expression: None, expected: Type::Pointer, found: Some(Type::from(v)) }),
         }
     }
 }
\ No newline at end of file
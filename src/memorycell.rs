@@ -1,59 +1,161 @@
 use crate::error::EvalError;
-use crate::value::Value;
+use crate::value::{Value, Shape};
+use crate::identifier::Identifier;
+use crate::r#type::Type;
+use crate::memory::Address;
+use std::fmt::Display;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum MemoryCell {
     NotAllocated,
-    AllocatedCell(AllocatedCell)
+    /// a heap cell given back by `free` or by a reallocation, until it is allocated again
+    Freed,
+    AllocatedCell(AllocatedCell),
+    /// a tuple, an array or a struct, each component has its own cell so that it has its own address
+    Compound{mutable: bool, shape: Shape, fields: Vec<MemoryCell>},
+    /// an array or the buffer of a `Vec` in the heap: its elements are in the contiguous
+    /// heap cells `start..start + len`, the block has room for `cap` elements
+    HeapArray{start: usize, len: usize, cap: usize},
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AllocatedCell {
     mutable: bool,
     value: Option<Value>,
+    moved: bool,
+    /// type of the values the cell may hold, none until the first write in a heap cell
+    r#type: Option<Type>,
 }
 
 #[allow(unused)]
 impl MemoryCell {
 
-    /// a modifier
+    // a modifier
     // pub fn new() -> Self { MemoryCell::NotAllocated }
 
     pub fn new_initialized(mutable: bool, value: Value) -> Self {
-        MemoryCell::AllocatedCell( AllocatedCell { mutable, value: Some(value) } )
+        match value.into_components() {
+            Ok((shape, vs)) => MemoryCell::Compound { mutable, shape, fields: vs.into_iter().map(|v| MemoryCell::new_initialized(mutable, v)).collect() },
+            Err(value) => MemoryCell::AllocatedCell( AllocatedCell { mutable, r#type: Some(Type::from(&value)), value: Some(value), moved: false } ),
+        }
     }
 
     pub fn new_uninitialized() -> Self {
-        MemoryCell::AllocatedCell( AllocatedCell { mutable: true, value: None } )
+        MemoryCell::AllocatedCell( AllocatedCell { mutable: true, value: None, moved: false, r#type: None } )
+    }
+
+    /// type of the values the cell may hold, that of its first value
+    pub fn r#type(&self) -> Option<Type> {
+        match self {
+            MemoryCell::AllocatedCell(ac) => ac.r#type.clone(),
+            MemoryCell::Compound { shape, fields, .. } => Some(shape.r#type(fields.iter().map(|mc| mc.r#type()).collect::<Option<_>>()?)),
+            _ => None,
+        }
+    }
+
+    /// a value of another type than the cell's cannot be written in it
+    pub fn check_type(&self, v: &Value) -> Result<(), EvalError> {
+        match self.r#type() {
+            Some(t) if t != Type::from(v) => Err(EvalError::TypeMismatch { expression: None, expected: t, found: Some(Type::from(v)) }),
+            _ => Ok(()),
+        }
     }
 
     pub fn is_mutable(&self) -> bool {
         match self {
-            MemoryCell::NotAllocated => false,
-            MemoryCell::AllocatedCell(ac) => ac.is_mutable()
+            MemoryCell::NotAllocated | MemoryCell::Freed => false,
+            MemoryCell::AllocatedCell(ac) => ac.is_mutable(),
+            MemoryCell::Compound { mutable, .. } => *mutable,
+            MemoryCell::HeapArray { .. } => true,
+        }
+    }
+
+    /// number of elements of an array
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            MemoryCell::Compound { shape: Shape::Array, fields, .. } => Some(fields.len()),
+            MemoryCell::HeapArray { len, .. } => Some(*len),
+            _ => None,
+        }
+    }
+
+    /// position of the component `field` of a tuple or of a struct
+    pub fn field_index(&self, field: &Identifier) -> Option<usize> {
+        match self {
+            MemoryCell::Compound { shape, fields, .. } => shape.index(field).filter(|i| *i < fields.len()),
+            _ => None,
+        }
+    }
+
+    pub fn field(&self, index: usize) -> Option<&MemoryCell> {
+        match self {
+            MemoryCell::Compound { fields, .. } => fields.get(index),
+            _ => None,
+        }
+    }
+
+    pub fn field_mut(&mut self, index: usize) -> Option<&mut MemoryCell> {
+        match self {
+            MemoryCell::Compound { fields, .. } => fields.get_mut(index),
+            _ => None,
         }
     }
 
     pub fn get_value(&self) -> Result<Value, EvalError> {
         match self {
             MemoryCell::NotAllocated => Err(EvalError::NonAllocatedCell(None)),
+            MemoryCell::Freed => Err(EvalError::UseAfterFree(None)),
+            MemoryCell::AllocatedCell(ac) if ac.moved => Err(EvalError::MovedValue(None)),
             MemoryCell::AllocatedCell(ac) =>
                 match &ac.value {
                     None => Err(EvalError::NonInitializedValue(None)),
                     Some(Value::Unit) => Ok(Value::Unit),
-                    Some(Value::Integer(i)) => Ok(Value::Integer(*i)),
+                    Some(Value::Integer(i, t)) => Ok(Value::Integer(*i, *t)),
                     Some(Value::Boolean(b)) => Ok(Value::Boolean(*b)),
-                    Some(Value::Pointer(a)) => Ok(Value::Pointer(a.clone()))
-                }
+                    Some(Value::Pointer(a)) => Ok(Value::Pointer(a.clone())),
+                    Some(Value::Range(r)) => Ok(Value::Range(*r)),
+                    Some(Value::Char(c)) => Ok(Value::Char(*c)),
+                    Some(Value::Str(s)) => Ok(Value::Str(s.clone())),
+                    Some(Value::String(p)) => Ok(Value::String(p.clone())),
+                    Some(Value::Vec(p)) => Ok(Value::Vec(p.clone())),
+                    Some(v @ Value::Variant(..)) => Ok(v.clone()),
+                    Some(Value::Tuple(_) | Value::Array(_) | Value::Struct(..)) => unreachable!("a tuple, an array or a struct is stored in a compound cell"),
+                },
+            MemoryCell::Compound { shape, fields, .. } => Ok(shape.build(fields.iter().map(|mc| mc.get_value()).collect::<Result<_, _>>()?)),
+            MemoryCell::HeapArray { .. } => unreachable!("the elements of a heap array are read by Memory"),
+        }
+    }
+
+    /// reads the value, an owned value is moved out of the cell
+    pub fn take_value(&mut self) -> Result<Value, EvalError> {
+        let v = self.get_value()?;
+        match self {
+            MemoryCell::AllocatedCell(ac) if v.is_owned() => ac.moved = true,
+            MemoryCell::Compound { fields, .. } => { fields.iter_mut().try_for_each(|mc| mc.take_value().map(|_| ()))? },
+            _ => (),
+        }
+        Ok(v)
+    }
+
+    /// the owned values still in the cell (or in its components), which must be dropped with it
+    pub fn owned_values(&self) -> Vec<Value> {
+        match self {
+            MemoryCell::AllocatedCell(AllocatedCell { value: Some(v), moved: false, .. }) => v.owned_parts(),
+            MemoryCell::Compound { fields, .. } => fields.iter().flat_map(|mc| mc.owned_values()).collect(),
+            _ => vec![],
         }
     }
 
     pub fn set_value(&mut self, v: Value) -> Result<(), EvalError> {
         match self {
             MemoryCell::NotAllocated => Err(EvalError::NonAllocatedCell(None)),
-            MemoryCell::AllocatedCell(ac) => {
-                if !ac.is_mutable() { return Err(EvalError::NotMutable(None)) }
-                ac.value = Some(v);
+            MemoryCell::Freed => Err(EvalError::UseAfterFree(None)),
+            MemoryCell::AllocatedCell(AllocatedCell { mutable: false, .. }) | MemoryCell::Compound { mutable: false, .. } =>
+                Err(EvalError::NotMutable(None)),
+            // un tuple ou une structure écrit dans la cellule lui donne des composantes
+            _ => {
+                self.check_type(&v)?;
+                *self = MemoryCell::new_initialized(true, v);
                 Ok(())
             }
         }
@@ -61,12 +163,40 @@ impl MemoryCell {
 
     pub fn is_allocated(&self) -> bool {
         match self {
-            MemoryCell::NotAllocated => false,
-            MemoryCell::AllocatedCell(_) => true
+            MemoryCell::NotAllocated | MemoryCell::Freed => false,
+            MemoryCell::AllocatedCell(_) | MemoryCell::Compound { .. } | MemoryCell::HeapArray { .. } => true
         }
     }
 }
 
 impl AllocatedCell {
     pub fn is_mutable(&self) -> bool { self.mutable }
-}
\ No newline at end of file
+}
+
+impl Display for MemoryCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemoryCell::NotAllocated => write!(f, "not allocated"),
+            MemoryCell::Freed => write!(f, "freed"),
+            MemoryCell::AllocatedCell(AllocatedCell { mutable, value, moved, .. }) => {
+                let status = if *mutable { "allocated, mutable" } else { "allocated" };
+                match value {
+                    Some(_) if *moved => write!(f, "{}, moved", status),
+                    None => write!(f, "{}, not initialized", status),
+                    Some(v) => write!(f, "{}, initialized : {} = {}", status, Type::from(v), v),
+                }
+            },
+            MemoryCell::HeapArray { start, len, cap } if len == cap =>
+                write!(f, "allocated, mutable, array in {}..{}", Address::HeapAddress(*start), Address::HeapAddress(start + len)),
+            MemoryCell::HeapArray { start, len, cap } =>
+                write!(f, "allocated, mutable, {} elements in {}..{}, capacity {}", len, Address::HeapAddress(*start), Address::HeapAddress(start + cap), cap),
+            MemoryCell::Compound { mutable, fields, .. } => {
+                let status = if *mutable { "allocated, mutable" } else { "allocated" };
+                match self.get_value() {
+                    Ok(v) => write!(f, "{}, initialized : {} = {}", status, Type::from(&v), v),
+                    Err(_) => write!(f, "{}, ({})", status, fields.iter().map(|mc| mc.to_string()).collect::<Vec<_>>().join(", ")),
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use crate::{error::EvalError, identifier::Identifier, value::Value, memorycell::MemoryCell};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NameSpace(HashMap<Identifier, MemoryCell>);
 
 
@@ -14,7 +14,7 @@ impl NameSpace {
     /// declare a new memory cell
     pub fn declare(&mut self, id: &Identifier, mutable: bool, value: Value) -> Result<(), EvalError> {
 //        self.0.try_insert(id, value).map_err(|_| EvalError::AlreadyDefined(id))
-        if self.0.contains_key(&id) {
+        if self.0.contains_key(id) {
             Err(EvalError::AlreadyDefined(id.clone()))
         } else {
             self.0.insert(id.clone(), MemoryCell::new_initialized(mutable, value));
@@ -38,10 +38,38 @@ impl NameSpace {
         }
     }
 
+    /// read the value of a memory cell, moving it out if it is owned
+    pub fn take(&mut self, id: &Identifier) -> Result<Value, EvalError> {
+        match self.0.get_mut(id) {
+            Some(mc) => mc.take_value(),
+            None => Err(EvalError::Undefined(id.clone())),
+        }
+    }
+
+    /// owned values that must be dropped with the namespace
+    pub fn owned_values(&self) -> Vec<Value> {
+        self.0.values().flat_map(|mc| mc.owned_values()).collect()
+    }
+
+    pub fn cell(&self, id: &Identifier) -> Option<&MemoryCell> {
+        self.0.get(id)
+    }
+
+    pub fn cell_mut(&mut self, id: &Identifier) -> Option<&mut MemoryCell> {
+        self.0.get_mut(id)
+    }
+
     /// returns true if the namespace has an identifier
     pub fn contains(&self, id: &Identifier) -> bool {
         self.0.contains_key(id)
     }
+
+    /// memory cells of the namespace, sorted by identifier
+    pub fn bindings(&self) -> Vec<(&Identifier, &MemoryCell)> {
+        let mut bindings = self.0.iter().collect::<Vec<_>>();
+        bindings.sort_by_key(|(id, _)| *id);
+        bindings
+    }
 }
 
 
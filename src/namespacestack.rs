@@ -1,13 +1,42 @@
-use crate::{error::EvalError, identifier::Identifier, namespace::NameSpace, value::Value, memory::Address};
+use std::fmt::Display;
+use crate::{error::EvalError, identifier::Identifier, namespace::NameSpace, value::Value, memory::Address, r#type::Type, memorycell::MemoryCell};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NameSpaceStack {
     stack: Vec<NameSpace>,
+    frames: Vec<(usize, Identifier)>,
 }
 
 impl NameSpaceStack {
     pub fn new() -> Self {
-        NameSpaceStack { stack: vec![] }
+        NameSpaceStack { stack: vec![], frames: vec![] }
+    }
+
+    /// index of the first namespace visible from the current function call
+    fn base(&self) -> usize {
+        self.frames.last().map(|(base, _)| *base).unwrap_or(0)
+    }
+
+    /// start a call to `name`: namespaces below `ns` become invisible (lexical scoping)
+    pub fn push_frame(&mut self, name: &Identifier, ns: NameSpace) {
+        self.frames.push((self.stack.len(), name.clone()));
+        self.stack.push(ns);
+    }
+
+    /// end a function call, returning every namespace it pushed
+    pub fn pop_frame(&mut self) -> Vec<NameSpace> {
+        let (base, _) = self.frames.pop().unwrap();
+        self.stack.split_off(base)
+    }
+
+    /// number of function calls in progress
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// names of the functions in progress, outermost first
+    pub fn calls(&self) -> Vec<Identifier> {
+        self.frames.iter().map(|(_, name)| name.clone()).collect()
     }
 
     pub fn push(&mut self, ns: NameSpace) {
@@ -23,16 +52,26 @@ impl NameSpaceStack {
     }
 
     pub fn find(&self, id: &Identifier) -> Result<Value, EvalError> {
-        for ns in self.stack.iter().rev() {
-            if let Ok(v) = ns.find(id) {
-                return Ok(v);
+        for ns in self.stack[self.base()..].iter().rev() {
+            if ns.contains(id) {
+                return ns.find(id);
             }
         }
         Err(EvalError::Undefined(id.clone()))
     }
 
+    /// like `find`, but an owned value is moved out of its variable
+    pub fn take(&mut self, id: &Identifier) -> Result<Value, EvalError> {
+        let base = self.base();
+        for ns in self.stack[base..].iter_mut().rev() {
+            if ns.contains(id) { return ns.take(id) }
+        }
+        Err(EvalError::Undefined(id.clone()))
+    }
+
     pub fn set(&mut self, id: &Identifier, value: &Value) -> Result<(), EvalError> {
-        for ns in self.stack.iter_mut().rev() {
+        let base = self.base();
+        for ns in self.stack[base..].iter_mut().rev() {
             let res = ns.set(id, value.clone());
             match res {
                 Ok(_) => return Ok(()),
@@ -45,10 +84,24 @@ impl NameSpaceStack {
         Err(EvalError::Undefined(id.clone()))
     }
 
+    /// cell at a stack address, the namespace may have been popped since the address was taken
+    pub fn cell_at(&self, index: usize, id: &Identifier) -> Result<&MemoryCell, EvalError> {
+        self.stack.get(index).and_then(|ns| ns.cell(id)).ok_or(EvalError::NonAllocatedCell(None))
+    }
+
+    pub fn cell_at_mut(&mut self, index: usize, id: &Identifier) -> Result<&mut MemoryCell, EvalError> {
+        self.stack.get_mut(index).and_then(|ns| ns.cell_mut(id)).ok_or(EvalError::NonAllocatedCell(None))
+    }
+
+    /// identifiers visible from the current function call
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        self.stack[self.base()..].iter().flat_map(|ns| ns.bindings().into_iter().map(|(id, _)| id.clone())).collect()
+    }
+
     pub fn get_address(&self, id: &Identifier) -> Result<Address, EvalError> {
         // renvoie l'adresse de pile d'un identifiant (cf opérateur `&x`)
 
-        for index in  (0..self.stack.len()).rev() {
+        for index in  (self.base()..self.stack.len()).rev() {
             if self.stack[index].contains(id) {
                 return Ok(Address::StackAddress(index, id.clone()))
             }
@@ -58,6 +111,26 @@ impl NameSpaceStack {
     }
 }
 
+/// every namespace, outermost first, with the function call it belongs to
+impl Display for NameSpaceStack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, ns) in self.stack.iter().enumerate() {
+            match self.frames.iter().find(|(base, _)| *base == index) {
+                Some((_, name)) => writeln!(f, "[{}] {}()", index, name)?,
+                None => writeln!(f, "[{}]", index)?,
+            }
+            for (id, cell) in ns.bindings() {
+                let id = if cell.is_mutable() { format!("mut {}", id) } else { id.to_string() };
+                match cell.get_value() {
+                    Ok(v) => writeln!(f, "    {} : {} = {}", id, Type::from(&v), v)?,
+                    Err(_) => writeln!(f, "    {} : {}", id, cell)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 /*
 #[cfg(test)]
 mod test_namespace_stack {
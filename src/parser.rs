@@ -1,10 +1,16 @@
 use std::fmt::{self, Display};
+use crate::r#type::IntType;
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum ParseError {
     CannotParse,
     SyntaxNotSupported,
+    ChainedOperators(String, String),
+    MissingSemicolon(String),
+    IntegerOutOfRange(String, IntType),
+    InvalidEscape(String),
+    InvalidRangePattern(String),
 }
 
 impl Display for ParseError {
@@ -13,6 +19,11 @@ impl Display for ParseError {
         match self {
             CannotParse => write!(f, "Cannot parse"),
             SyntaxNotSupported => write!(f, "Syntax not supported"),
+            ChainedOperators(op1, op2) => write!(f, "Operators `{}` and `{}` cannot be chained, use parentheses", op1, op2),
+            IntegerOutOfRange(literal, t) => write!(f, "Integer literal `{}` is out of range for `{}`", literal, t),
+            InvalidEscape(escape) => write!(f, "Invalid escape or character literal `{}`", escape),
+            InvalidRangePattern(pattern) => write!(f, "Range pattern `{}` is empty or mixes types", pattern),
+            MissingSemicolon(instr) => write!(f, "Expected `;` after `{}`", instr),
         }
     }
 }
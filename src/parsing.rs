@@ -3,8 +3,13 @@ extern crate pest_derive;
 extern crate lazy_static;
 
 pub mod binop;
+pub mod unop;
 pub mod expression;
 pub mod instruction;
+pub mod function;
+pub mod structure;
+pub mod pattern;
+pub mod program;
 pub mod utils;
 pub mod parsedvalue;
 
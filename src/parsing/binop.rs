@@ -14,7 +14,13 @@ pub enum Binop {
     Neq,
 
     And,
-    Or
+    Or,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
 }
 
 use std::fmt::{self, Display};
@@ -36,6 +42,11 @@ impl Display for Binop {
             Neq => write!(f, "!="),
             And => write!(f, "&&"),
             Or => write!(f, "||"),
+            BitAnd => write!(f, "&"),
+            BitOr => write!(f, "|"),
+            BitXor => write!(f, "^"),
+            Shl => write!(f, "<<"),
+            Shr => write!(f, ">>"),
         }
     }
 }
@@ -50,7 +61,7 @@ use crate::parser::{Parse, ParseError};
 impl Parse for Binop {
 
     fn parse(input: &str) -> Result<Self, ParseError> {
-        match PestParser::parse(Rule::start_rule_binop, &input) {
+        match PestParser::parse(Rule::start_rule_binop, input) {
             Ok(mut pairs) => {
                 let first_rule = pairs.next().unwrap();
                 match first_rule.as_rule() {
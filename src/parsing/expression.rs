@@ -1,33 +1,99 @@
 use super::binop::Binop;
+use super::unop::Unop;
 use super::parsedvalue::ParsedValue;
+use super::instruction::Instruction;
+use super::pattern::Pattern;
+use super::structure::Fields;
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     Const(ParsedValue),
     Identifier(Identifier),
     BinOp(Box<Expression>, Binop, Box<Expression>),
-    Conditional{
+    UnOp(Unop, Box<Expression>),
+    Block(Vec<Instruction>, Option<Box<Expression>>),
+    IfElse{
         cond: Box<Expression>,
         cond_true: Box<Expression>,
         cond_false: Box<Expression>,
     },
+    Call(Identifier, Vec<Expression>),
+    Loop{label: Option<Identifier>, body: Box<Expression>},
+    Range{start: Box<Expression>, end: Box<Expression>, inclusive: bool},
+    MethodCall(Box<Expression>, Identifier, Vec<Expression>),
+    AssocCall(Identifier, Identifier, Vec<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+    Tuple(Vec<Expression>),
+    /// `[a, b, c]`
+    Array(Vec<Expression>),
+    /// `[e; n]`
+    ArrayRepeat(Box<Expression>, Box<Expression>),
+    /// `vec![a, b, c]` or `vec![e; n]`, the array the `Vec` is built from
+    VecMacro(Box<Expression>),
+    /// `Name { field: expr, ... }`
+    Struct(Identifier, Vec<(Identifier, Expression)>),
+    /// `t.0` or `s.f`
+    Field(Box<Expression>, Identifier),
+    /// `Enum::Variant` or `Enum::Variant { f: e, ... }`
+    Variant(Identifier, Identifier, Fields<Expression>),
+    Match(Box<Expression>, Vec<MatchArm>),
     NewPtr,
     Deref(Box<Expression>),
     AmpersAnd(Box<Expression>),
 }
 
 
+/// `pattern if guard => body`
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+}
+
 use std::fmt::Display;
 
+impl Display for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.guard {
+            Some(guard) => write!(f, "{} if {} => {}", self.pattern, guard, self.body),
+            None => write!(f, "{} => {}", self.pattern, self.body),
+        }
+    }
+}
+
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Expression::*;
         match self {
             Const(i) => write!(f, "{}", i),
             BinOp(lhs, op, rhs) => write!(f, "({} {} {})", lhs, op, rhs),
-            Conditional { cond, cond_true, cond_false } => 
-                write!(f, "({}) ? {}  : {} ", cond, cond_true, cond_false),
+            UnOp(op, e) => write!(f, "{}{}", op, e),
+            Block(instrs, tail) => write!(f, "{{{}{}}}",
+                instrs.iter().map(|x| format!("{};", x)).collect::<Vec<_>>().join(""),
+                tail.as_ref().map(|e| e.to_string()).unwrap_or_default()),
+            IfElse { cond, cond_true, cond_false } =>
+                write!(f, "if {} {} else {}", cond, cond_true, cond_false),
             Identifier(id) => write!(f, "{}", id),
+            Call(id, args) => write!(f, "{}({})", id, args.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
+            Loop { label: Some(label), body } => write!(f, "{}: loop {}", label, body),
+            Loop { label: None, body } => write!(f, "loop {}", body),
+            Range { start, end, inclusive: false } => write!(f, "({}..{})", start, end),
+            Range { start, end, inclusive: true } => write!(f, "({}..={})", start, end),
+            MethodCall(e, method, args) => write!(f, "{}.{}({})", e, method, args.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
+            AssocCall(r#type, id, args) => write!(f, "{}::{}({})", r#type, id, args.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
+            Index(e, index) if matches!(**e, Deref(_)) => write!(f, "({})[{}]", e, index),
+            Index(e, index) => write!(f, "{}[{}]", e, index),
+            Tuple(es) if es.len() == 1 => write!(f, "({},)", es[0]),
+            Tuple(es) => write!(f, "({})", es.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
+            Array(es) => write!(f, "[{}]", es.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
+            ArrayRepeat(e, n) => write!(f, "[{}; {}]", e, n),
+            VecMacro(e) => write!(f, "vec!{}", e),
+            Struct(name, fields) => write!(f, "{} {{ {} }}", name, fields.iter().map(|(id, e)| format!("{}: {}", id, e)).collect::<Vec<_>>().join(", ")),
+            Variant(name, variant, fields) => write!(f, "{}::{}{}", name, variant, fields),
+            Match(e, arms) => write!(f, "match {} {{ {} }}", e, arms.iter().map(|arm| arm.to_string()).collect::<Vec<_>>().join(", ")),
+            Field(e, field) if matches!(**e, Deref(_)) => write!(f, "({}).{}", e, field),
+            Field(e, field) => write!(f, "{}.{}", e, field),
             NewPtr => write!(f,  "Ptr::new()"),
             Deref(e) => write!(f, "*{}", e),
             AmpersAnd(e) => write!(f, "&{}", e),
@@ -45,12 +111,12 @@ use crate::{identifier::Identifier, parser::{ Parse, ParseError}};
 impl Parse for Expression {
 
     fn parse(input: &str) -> Result<Self, ParseError> {
-        match PestParser::parse(Rule::start_rule_expr, &input) {
+        match PestParser::parse(Rule::start_rule_expr, input) {
             Ok(mut pairs) => {
                 let first_rule = pairs.next().unwrap();
                 match first_rule.as_rule() {
                     Rule::expr => {
-                        Ok(parse_expr(first_rule.into_inner()))
+                        parse_expr(first_rule.into_inner())
                     }
                     _ => { panic!("the grammar is not as expected") }
                 }                
@@ -58,4 +124,39 @@ impl Parse for Expression {
             Err(_e) => { Err(ParseError::CannotParse) }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test_expression {
+    use super::*;
+    use Expression::*;
+
+    /// `input` parsed and displayed, every binary operation between parentheses
+    fn parenthesized(input: &str) -> String {
+        Expression::parse(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(parenthesized("a || b && c"), "(a || (b && c))");
+        assert_eq!(parenthesized("a && b || c"), "((a && b) || c)");
+        assert_eq!(parenthesized("a & b == c"), "((a & b) == c)");
+        assert_eq!(parenthesized("a | b ^ c & d"), "(a | (b ^ (c & d)))");
+        assert_eq!(parenthesized("1 + 2 << 3"), "((1 + 2) << 3)");
+        assert_eq!(parenthesized("a - b - c"), "((a - b) - c)");
+        assert_eq!(parenthesized("0..n + 1"), "(0..(n + 1))");
+        // les opérateurs unaires lient plus fort que les binaires, moins que `.` et `[]`
+        assert!(matches!(Expression::parse("-2 * 3").unwrap(), BinOp(lhs, Binop::Mul, _) if matches!(*lhs, UnOp(Unop::Neg, _))));
+        assert!(matches!(Expression::parse("!a && b").unwrap(), BinOp(lhs, Binop::And, _) if matches!(*lhs, UnOp(Unop::Not, _))));
+        assert!(matches!(Expression::parse("-x.f").unwrap(), UnOp(Unop::Neg, e) if matches!(*e, Field(..))));
+        assert!(matches!(Expression::parse("&a[0]").unwrap(), AmpersAnd(e) if matches!(*e, Index(..))));
+    }
+
+    #[test]
+    fn test_chained_operators() {
+        assert!(matches!(Expression::parse("a < b < c"), Err(ParseError::ChainedOperators(op1, op2)) if op1 == "<" && op2 == "<"));
+        assert!(matches!(Expression::parse("a == b != c"), Err(ParseError::ChainedOperators(op1, op2)) if op1 == "==" && op2 == "!="));
+        assert!(matches!(Expression::parse("a < b == c"), Err(ParseError::ChainedOperators(..))));
+        assert_eq!(parenthesized("(a < b) == c"), "((a < b) == c)");
+        assert_eq!(parenthesized("a < b && b < c"), "((a < b) && (b < c))");
+    }
+}
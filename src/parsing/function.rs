@@ -0,0 +1,37 @@
+use crate::identifier::Identifier;
+use crate::parsing::expression::Expression;
+use crate::r#type::Type;
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub id: Identifier,
+    pub mutable: bool,
+    pub r#type: Type,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: Identifier,
+    pub params: Vec<Param>,
+    pub ret: Type,
+    pub body: Box<Expression>,
+}
+
+use std::fmt::Display;
+
+impl Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.mutable {
+            write!(f, "mut {}: {}", self.id, self.r#type)
+        } else {
+            write!(f, "{}: {}", self.id, self.r#type)
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params = self.params.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+        write!(f, "fn {}({}) -> {} {}", self.name, params, self.ret, self.body)
+    }
+}
@@ -1,19 +1,27 @@
 use crate::parsing::expression::Expression;
 use crate::identifier::Identifier;
+use crate::parsing::function::Function;
+use crate::parsing::structure::{Struct, Enum};
+use crate::parsing::binop::Binop;
+use crate::parsing::pattern::Pattern;
+use crate::r#type::Type;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Instruction {
     Expr(Expression),
-    Let{id:Identifier, mutable:bool, expr:Expression},
-    Block(Vec<Instruction>),
-    IfElse{
-        cond: Expression,
-        cond_true: Box<Instruction>,
-        cond_false: Box<Instruction>,
-    },
-    While(Expression, Box<Instruction>),
-    WriteAt(Expression, Expression),
+    /// `let pattern: type = expr`, the type annotation is optional
+    Let{pattern: Pattern, r#type: Option<Type>, expr: Expression},
+    While{label: Option<Identifier>, cond: Expression, body: Box<Expression>},
+    For{label: Option<Identifier>, id: Identifier, iter: Expression, body: Box<Expression>},
+    WriteAt(Expression, Option<Binop>, Expression),
     Free(Expression),
+    FnDef(Rc<Function>),
+    StructDef(Rc<Struct>),
+    EnumDef(Rc<Enum>),
+    Break(Option<Identifier>, Option<Expression>),
+    Continue(Option<Identifier>),
+    Return(Option<Expression>),
 } 
 
 use std::fmt::Display;
@@ -22,28 +30,37 @@ impl Display for Instruction {
         use Instruction::*;
         match self {
             Expr(expr) => write!(f, "{}", expr),
-            Let{id, mutable, expr} => {
-                if *mutable {
-                    write!(f, "let mut {} = {}", id, expr)
-                } else {
-                    write!(f, "let {} = {}", id, expr)
-                }
+            Let{pattern, r#type: None, expr} => write!(f, "let {} = {}", pattern, expr),
+            Let{pattern, r#type: Some(t), expr} => write!(f, "let {}: {} = {}", pattern, t, expr),
+            While{label: Some(label), cond, body} => {
+                write!(f, "{}: while {} {}", label, cond, body)
             },
-            Block(instrs) => {
-                write!(f, "{{{}}}", instrs.into_iter().map(|x| x.to_string()).collect::<Vec<_>>().join(";"))
+            While{label: None, cond, body} => {
+                write!(f, "while {} {}", cond, body)
             },
-            IfElse{cond, cond_true, cond_false} => {
-                write!(f, "if {} {} else {}", cond, cond_true, cond_false)
+            For{label: Some(label), id, iter, body} => {
+                write!(f, "{}: for {} in {} {}", label, id, iter, body)
             },
-            While(cond, instr) => {
-                write!(f, "while {} {}", cond, instr)
+            For{label: None, id, iter, body} => {
+                write!(f, "for {} in {} {}", id, iter, body)
             },
-            WriteAt(lexpr, expr) => {
+            WriteAt(lexpr, None, expr) => {
                 write!(f, "{} = {}", lexpr, expr)
             },
+            WriteAt(lexpr, Some(op), expr) => {
+                write!(f, "{} {}= {}", lexpr, op, expr)
+            },
             Free(lexpr) => {
                 write!(f, "free {}", lexpr)
             },
+            FnDef(fun) => write!(f, "{}", fun),
+            StructDef(def) => write!(f, "{}", def),
+            EnumDef(def) => write!(f, "{}", def),
+            Break(label, expr) => write!(f, "break{}{}",
+                label.as_ref().map(|l| format!(" {}", l)).unwrap_or_default(),
+                expr.as_ref().map(|e| format!(" {}", e)).unwrap_or_default()),
+            Continue(label) => write!(f, "continue{}", label.as_ref().map(|l| format!(" {}", l)).unwrap_or_default()),
+            Return(expr) => write!(f, "return{}", expr.as_ref().map(|e| format!(" {}", e)).unwrap_or_default()),
         }
     }
 }
@@ -55,7 +72,7 @@ use super::utils::{PestParser, Rule, parse_instr};
 
 impl Parse for Instruction {
     fn parse(input: &str) -> Result<Self, ParseError> {
-        match PestParser::parse(Rule::start_rule_instr, &input) {
+        match PestParser::parse(Rule::start_rule_instr, input) {
             Ok(mut pairs) => {
                 let first_rule = pairs.next().unwrap();
                 match first_rule.as_rule() {
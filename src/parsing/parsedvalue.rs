@@ -1,19 +1,27 @@
 use std::fmt;
+use std::rc::Rc;
+use crate::r#type::IntType;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParsedValue {
-    Integer(isize),
+    /// an integer literal and its suffix, `255u8`; without suffix its type is the one expected
+    Integer(i128, Option<IntType>),
     Boolean(bool),
     Unit,
+    Char(char),
+    Str(Rc<str>),
 }
 
 impl fmt::Display for ParsedValue {
 
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ParsedValue::Integer(i) => write!(f, "{}", i),
+            ParsedValue::Integer(i, None) => write!(f, "{}", i),
+            ParsedValue::Integer(i, Some(t)) => write!(f, "{}{}", i, t),
             ParsedValue::Boolean(b) => write!(f, "{}", b),
             ParsedValue::Unit => write!(f, "()"),
+            ParsedValue::Char(c) => write!(f, "{:?}", c),
+            ParsedValue::Str(s) => write!(f, "{:?}", s),
         }
     }
 }
\ No newline at end of file
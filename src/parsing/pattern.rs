@@ -0,0 +1,46 @@
+use crate::identifier::Identifier;
+use crate::parsing::parsedvalue::ParsedValue;
+
+/// left-hand side of a `let` or of a `match` arm: binds the parts of a value to variables
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Identifier{id: Identifier, mutable: bool},
+    Wildcard,
+    Literal(ParsedValue),
+    /// `a..=b` or `a..b` on integers or characters
+    Range{start: ParsedValue, end: ParsedValue, inclusive: bool},
+    Tuple(Vec<Pattern>),
+    /// `Name { f: p, .. }`, `rest` is true with `..`
+    Struct{name: Identifier, fields: Vec<(Identifier, Pattern)>, rest: bool},
+    /// `Enum::Variant`, its fields are a `Tuple` or a `Struct` pattern named after the variant
+    Variant(Identifier, Identifier, Option<Box<Pattern>>),
+}
+
+use std::fmt::Display;
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Identifier { id, mutable: true } => write!(f, "mut {}", id),
+            Pattern::Identifier { id, mutable: false } => write!(f, "{}", id),
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Literal(v) => write!(f, "{}", v),
+            Pattern::Range { start, end, inclusive: true } => write!(f, "{}..={}", start, end),
+            Pattern::Range { start, end, inclusive: false } => write!(f, "{}..{}", start, end),
+            Pattern::Tuple(patterns) if patterns.len() == 1 => write!(f, "({},)", patterns[0]),
+            Pattern::Tuple(patterns) => write!(f, "({})", patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")),
+            Pattern::Struct { name, fields, rest } => {
+                let mut fields = fields.iter().map(|(id, p)| format!("{}: {}", id, p)).collect::<Vec<_>>();
+                if *rest { fields.push("..".to_string()) }
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            },
+            Pattern::Variant(name, variant, None) => write!(f, "{}::{}", name, variant),
+            Pattern::Variant(name, variant, Some(fields)) => match &**fields {
+                Pattern::Tuple(patterns) => write!(f, "{}::{}({})", name, variant, patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")),
+                // `Variant { ... }`
+                fields => write!(f, "{}::{}", name, fields),
+            },
+        }
+    }
+}
+
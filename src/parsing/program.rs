@@ -0,0 +1,74 @@
+use crate::parsing::instruction::Instruction;
+use crate::parsing::expression::Expression;
+
+/// a whole source file: top-level items and statements, run in order,
+/// and a final expression without `;` giving the value of the program
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub instrs: Vec<Instruction>,
+    pub tail: Option<Expression>,
+}
+
+use std::fmt::Display;
+
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for instr in &self.instrs {
+            writeln!(f, "{};", instr)?;
+        }
+        match &self.tail {
+            Some(e) => writeln!(f, "{}", e),
+            None => Ok(()),
+        }
+    }
+}
+
+
+use pest::Parser;
+use crate::parser::{ParseError, Parse};
+use super::utils::{PestParser, Rule, parse_items};
+
+impl Parse for Program {
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        match PestParser::parse(Rule::start_rule_program, input) {
+            Ok(pairs) => {
+                let (instrs, tail) = parse_items(pairs)?;
+                Ok(Program { instrs, tail: tail.map(|e| *e) })
+            },
+            Err(_e) => { Err(ParseError::CannotParse) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_program {
+    use super::*;
+
+    /// `input` parsed and displayed, one instruction per line
+    fn parsed(input: &str) -> String {
+        Program::parse(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_whitespace_and_comments() {
+        assert_eq!(parsed("let\tx\t=\n1;\r\n// fin de ligne\nx"), "let x = 1;\nx\n");
+        assert_eq!(parsed("let x = 1; /* un /* commentaire */ imbriqué */ x"), "let x = 1;\nx\n");
+        // un commentaire imbriqué se ferme autant de fois qu'il s'ouvre
+        assert!(Program::parse("let x = 1; /* un /* commentaire */ x").is_err());
+        assert!(Program::parse("let x = 1; /* sans fin").is_err());
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(parsed("let _tmp = 1; let my_var = _tmp; my_var"), "let _tmp = 1;\nlet my_var = _tmp;\nmy_var\n");
+        // un identificateur peut commencer par un mot-clé, sans en être un
+        assert_eq!(parsed("let letter = 1; let iffy = 2; let while_ = 3; letter + iffy"), "let letter = 1;\nlet iffy = 2;\nlet while_ = 3;\n(letter + iffy)\n");
+        assert!(Program::parse("let if = 1;").is_err());
+        assert!(Program::parse("let 1x = 1;").is_err());
+    }
+
+    #[test]
+    fn test_integer_literals() {
+        assert_eq!(parsed("(0xFF, 0b1010, 0o17, 1_000_000, 0xFF_u8, 255u8)"), "(255, 10, 15, 1000000, 255u8, 255u8)\n");
+    }
+}
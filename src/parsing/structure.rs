@@ -0,0 +1,76 @@
+use crate::identifier::Identifier;
+use crate::r#type::Type;
+
+/// `struct Name { field: Type, ... }`, the fields are kept in declaration order
+#[derive(Debug, Clone)]
+pub struct Struct {
+    pub name: Identifier,
+    pub fields: Vec<(Identifier, Type)>,
+}
+
+use std::fmt::Display;
+
+impl Display for Struct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = self.fields.iter().map(|(id, t)| format!("{}: {}", id, t)).collect::<Vec<_>>().join(", ");
+        write!(f, "struct {} {{ {} }}", self.name, fields)
+    }
+}
+
+/// fields of an enum variant: `A`, `B(T, ...)` or `C { f: T, ... }`
+#[derive(Debug, Clone)]
+pub enum Fields<T> {
+    Unit,
+    Tuple(Vec<T>),
+    Struct(Vec<(Identifier, T)>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: Identifier,
+    pub fields: Fields<Type>,
+}
+
+/// `enum Name { Variant, ... }`
+#[derive(Debug, Clone)]
+pub struct Enum {
+    pub name: Identifier,
+    pub variants: Vec<Variant>,
+}
+
+impl<T: Display> Display for Fields<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fields::Unit => Ok(()),
+            Fields::Tuple(ts) => write!(f, "({})", ts.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ")),
+            Fields::Struct(fields) => write!(f, " {{ {} }}", fields.iter().map(|(id, t)| format!("{}: {}", id, t)).collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.name, self.fields)
+    }
+}
+
+impl Display for Enum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variants = self.variants.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+        write!(f, "enum {} {{ {} }}", self.name, variants)
+    }
+}
+
+impl Enum {
+    /// `enum Option { None, Some(T) }`, given by the `checked_*` methods when the program
+    /// does not define its own `Option`: `T` is not checked, only these methods build it
+    pub fn option() -> Enum {
+        Enum {
+            name: Identifier::from("Option"),
+            variants: vec![
+                Variant { name: Identifier::from("None"), fields: Fields::Unit },
+                Variant { name: Identifier::from("Some"), fields: Fields::Tuple(vec![Type::Named(Identifier::from("T"))]) },
+            ],
+        }
+    }
+}
@@ -0,0 +1,17 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unop {
+    Neg,
+    Not,
+}
+
+use std::fmt::{self, Display};
+
+impl Display for Unop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Unop::*;
+        match self {
+            Neg => write!(f, "-"),
+            Not => write!(f, "!"),
+        }
+    }
+}
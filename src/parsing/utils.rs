@@ -8,29 +8,53 @@ use pest::pratt_parser::PrattParser;
 pub struct PestParser;
 
 use super::instruction::Instruction;
-use super::expression::Expression;
+use super::expression::{Expression, MatchArm};
 use super::binop::Binop;
+use super::unop::Unop;
 use super::parsedvalue::ParsedValue;
+use super::function::{Function, Param};
+use super::structure::{Struct, Enum, Variant, Fields};
+use super::pattern::Pattern;
 
 use crate::identifier::Identifier;
+use std::rc::Rc;
 use crate::parser::ParseError;
+use crate::r#type::{Type, IntType};
 
 lazy_static::lazy_static! {
     static ref PRATT_PARSER: PrattParser<Rule> = {
         use pest::pratt_parser::{Assoc::*, Op};
         use Rule::*;
 
-        // Precedence is defined lowest to highest
+        // Precedence is defined lowest to highest, following
+        // https://doc.rust-lang.org/reference/expressions.html#expression-precedence
+        // ranges and comparisons are non associative, see `non_associative`
         PrattParser::new()
-            // Addition and subtract have equal precedence
-            .op(Op::infix(AND, Left) | Op::infix(OR, Left))
+            .op(Op::infix(RANGE, Left) | Op::infix(RANGE_INCLUSIVE, Left))
+            .op(Op::infix(OR, Left))
+            .op(Op::infix(AND, Left))
             .op(Op::infix(EQQUALS, Left) | Op::infix(NEQ, Left) | Op::infix(GEQ, Left) | Op::infix(LEQ, Left) | Op::infix(GREATER, Left) | Op::infix(LOWER, Left))
+            .op(Op::infix(BITOR, Left))
+            .op(Op::infix(BITXOR, Left))
+            .op(Op::infix(BITAND, Left))
+            .op(Op::infix(SHL, Left) | Op::infix(SHR, Left))
+            // Addition and subtract have equal precedence
             .op(Op::infix(ADD, Left) | Op::infix(SUBTRACT, Left))
             .op(Op::infix(MULTIPLY, Left) | Op::infix(DIVIDE, Left) | Op::infix(MODULO, Left))
-//            .op(Op::prefix(unary_minus))
+            .op(Op::prefix(NEG) | Op::prefix(NOT) | Op::prefix(deref) | Op::prefix(ampersand))
+            .op(Op::postfix(method_call) | Op::postfix(field) | Op::postfix(index))
     };
 }
 
+/// precedence level of the non associative operators: `a < b < c` and `a..b..c` are rejected
+fn non_associative(rule: Rule) -> Option<u8> {
+    match rule {
+        Rule::EQQUALS | Rule::NEQ | Rule::GEQ | Rule::LEQ | Rule::GREATER | Rule::LOWER => Some(0),
+        Rule::RANGE | Rule::RANGE_INCLUSIVE => Some(1),
+        _ => None
+    }
+}
+
 pub fn parse_binop(mut pairs: Pairs<Rule>) -> Binop {
     let first_rule = pairs.next().unwrap();
     parse_binop_rule(first_rule)
@@ -51,113 +75,504 @@ pub fn parse_binop_rule(first_rule: Pair<'_, Rule>) -> Binop {
         Rule::LOWER => Binop::Lt,
         Rule::AND => Binop::And,
         Rule::OR => Binop::Or,
+        Rule::BITAND => Binop::BitAnd,
+        Rule::BITOR => Binop::BitOr,
+        Rule::BITXOR => Binop::BitXor,
+        Rule::SHL => Binop::Shl,
+        Rule::SHR => Binop::Shr,
         _ => unreachable!()
     }
 }
 
 
-pub fn parse_expr(pairs: Pairs<Rule>) -> Expression {
+pub fn parse_expr(pairs: Pairs<Rule>) -> Result<Expression, ParseError> {
+    parse_operators(pairs).map(|(expr, _)| expr)
+}
+
+/// parses an integer literal: decimal, `0x`, `0o` or `0b`, with `_` separators and a type suffix;
+/// a literal without suffix may be typed `u64` later, and `128i8` may be negated: the range
+/// of the type is checked again when the literal is evaluated
+fn parse_integer(literal: &str) -> Result<(i128, Option<IntType>), ParseError> {
+    let suffix = IntType::ALL.into_iter().find(|t| literal.ends_with(&t.to_string()));
+    let digits = literal[..literal.len() - suffix.map_or(0, |t| t.to_string().len())].replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, &digits[..]),
+    };
+    let out_of_range = || ParseError::IntegerOutOfRange(literal.to_string(), suffix.unwrap_or_default());
+    let i = u64::from_str_radix(digits, radix).map_err(|_| out_of_range())? as i128;
+    match suffix {
+        Some(t) if !t.contains(i) && !t.contains(-i) => Err(out_of_range()),
+        _ => Ok((i, suffix)),
+    }
+}
+
+/// replaces the escapes of a char or string literal by the characters they stand for,
+/// a `\` at the end of a line skips the line break and the indentation that follows
+fn unescape(literal: &str) -> Result<String, ParseError> {
+    let mut res = String::new();
+    let mut chars = literal.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' { res.push(c); continue }
+        let escape = match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '\'' | '"')) => c,
+            Some('\n') => {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                continue
+            },
+            Some('x') => {
+                let code: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&code, 16).ok().filter(|b| b.is_ascii()).map(char::from)
+                    .ok_or(ParseError::InvalidEscape(format!("\\x{}", code)))?
+            },
+            Some('u') => {
+                let code: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                code.strip_prefix('{').and_then(|code| u32::from_str_radix(code, 16).ok()).and_then(char::from_u32)
+                    .ok_or(ParseError::InvalidEscape(format!("\\u{}}}", code)))?
+            },
+            c => return Err(ParseError::InvalidEscape(format!("\\{}", c.map(String::from).unwrap_or_default()))),
+        };
+        res.push(escape);
+    }
+    Ok(res)
+}
+
+/// parses an expression, also returning its outermost non associative operator
+/// (parenthesized sub-expressions are primaries, so they have none)
+fn parse_operators(pairs: Pairs<Rule>) -> Result<(Expression, Option<Pair<Rule>>), ParseError> {
     PRATT_PARSER
-        .map_primary(|primary| match primary.as_rule() {
-            Rule::integer => Expression::Const(ParsedValue::Integer(primary.as_str().parse::<isize>().unwrap())),
+        .map_primary(|primary| Ok((match primary.as_rule() {
+            Rule::integer => {
+                let (i, suffix) = parse_integer(primary.as_str())?;
+                Expression::Const(ParsedValue::Integer(i, suffix))
+            },
             Rule::boolean => Expression::Const(ParsedValue::Boolean(match primary.as_str() {
                 "true" => true,
                 "false" => false,
                 _ => unreachable!()
             })),
-            Rule::expr => parse_expr(primary.into_inner()),
-            Rule::atom => parse_expr(primary.into_inner()),
-            Rule::conditional_expr => {
+            Rule::expr | Rule::cond => parse_expr(primary.into_inner())?,
+            Rule::atom => parse_expr(primary.into_inner())?,
+            Rule::instrs => parse_block(primary)?,
+            Rule::if_expr => {
                 let mut rules = primary.into_inner();
-                let cond = Box::new(parse_expr(rules.next().unwrap().into_inner()));
-                let cond_true = Box::new(parse_expr(rules.next().unwrap().into_inner()));
-                let cond_false = Box::new(parse_expr(rules.next().unwrap().into_inner()));
-                Expression::Conditional{cond, cond_true, cond_false}
+                let cond = Box::new(parse_expr(rules.next().unwrap().into_inner())?);
+                let cond_true = Box::new(parse_block(rules.next().unwrap())?);
+                // `else if` is kept as a nested `IfElse`, a missing `else` is an empty block
+                let cond_false = Box::new(match rules.next() {
+                    Some(rule) => parse_expr(Pairs::single(rule))?,
+                    None => Expression::Block(vec![], None),
+                });
+                Expression::IfElse{cond, cond_true, cond_false}
             },
             Rule::unit => Expression::Const(ParsedValue::Unit),
+            Rule::tuple => Expression::Tuple(primary.into_inner().map(|rule| parse_expr(rule.into_inner())).collect::<Result<_, _>>()?),
+            Rule::array => Expression::Array(primary.into_inner().map(|rule| parse_expr(rule.into_inner())).collect::<Result<_, _>>()?),
+            Rule::array_repeat => {
+                let mut rules = primary.into_inner();
+                let e = Box::new(parse_expr(rules.next().unwrap().into_inner())?);
+                Expression::ArrayRepeat(e, Box::new(parse_expr(rules.next().unwrap().into_inner())?))
+            },
+            Rule::vec_macro => Expression::VecMacro(Box::new(parse_expr(primary.into_inner())?)),
+            Rule::struct_lit => {
+                let mut rules = primary.into_inner();
+                let name = Identifier::from(rules.next().unwrap().as_str());
+                Expression::Struct(name, parse_field_inits(rules)?)
+            },
+            Rule::variant_lit => {
+                let mut rules = primary.into_inner();
+                let name = Identifier::from(rules.next().unwrap().as_str());
+                let variant = Identifier::from(rules.next().unwrap().as_str());
+                Expression::Variant(name, variant, Fields::Struct(parse_field_inits(rules)?))
+            },
+            Rule::path => {
+                let mut rules = primary.into_inner();
+                let name = Identifier::from(rules.next().unwrap().as_str());
+                let variant = Identifier::from(rules.next().unwrap().as_str());
+                Expression::Variant(name, variant, Fields::Unit)
+            },
+            Rule::match_expr => {
+                let mut rules = primary.into_inner();
+                let e = Box::new(parse_expr(rules.next().unwrap().into_inner())?);
+                let arms = rules.map(|rule| {
+                    let mut rules = rule.into_inner().peekable();
+                    let pattern = parse_pattern(rules.next().unwrap())?;
+                    let guard = rules.next_if(|rule| rule.as_rule() == Rule::guard)
+                        .map(|rule| parse_expr(rule.into_inner().next().unwrap().into_inner())).transpose()?;
+                    let body = parse_expr(Pairs::single(rules.next().unwrap()))?;
+                    Ok(MatchArm { pattern, guard, body })
+                }).collect::<Result<_, _>>()?;
+                Expression::Match(e, arms)
+            },
+            Rule::char_lit => {
+                let text = unescape(primary.into_inner().next().unwrap().as_str())?;
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Expression::Const(ParsedValue::Char(c)),
+                    _ => return Err(ParseError::InvalidEscape(text)),
+                }
+            },
+            Rule::string_lit => Expression::Const(ParsedValue::Str(unescape(primary.into_inner().next().unwrap().as_str())?.into())),
             Rule::identifier => Expression::Identifier(Identifier::from(primary.as_str())),
-            Rule::ptrnew => Expression::NewPtr,
-            Rule::deref => {
-                let expr = Box::new(parse_expr(primary.into_inner()));
-                Expression::Deref(expr)
+            Rule::call => {
+                let mut rules = primary.into_inner();
+                let id = Identifier::from(rules.next().unwrap().as_str());
+                let args = rules.map(|rule| parse_expr(rule.into_inner())).collect::<Result<_, _>>()?;
+                Expression::Call(id, args)
+            },
+            Rule::assoc_call => {
+                let mut rules = primary.into_inner();
+                let r#type = Identifier::from(rules.next().unwrap().as_str());
+                let id = Identifier::from(rules.next().unwrap().as_str());
+                let args = rules.map(|rule| parse_expr(rule.into_inner())).collect::<Result<_, _>>()?;
+                Expression::AssocCall(r#type, id, args)
             },
-            Rule::ampersand => {
-                let expr = Box::new(parse_expr(primary.into_inner()));
-                Expression::AmpersAnd(expr)
+            Rule::loop_expr => {
+                let mut rules = primary.into_inner().peekable();
+                let label = rules.next_if(|rule| rule.as_rule() == Rule::label).map(|rule| Identifier::from(rule.as_str()));
+                let body = Box::new(parse_block(rules.next().unwrap())?);
+                Expression::Loop{label, body}
             },
+            Rule::ptrnew => Expression::NewPtr,
             rule => unreachable!("parse_expr expected atom, found {:?}", rule),
+        }, None::<Pair<Rule>>)))
+        .map_prefix(|op, expr| {
+            let expr = Box::new(expr?.0);
+            Ok((match op.as_rule() {
+                Rule::NEG => Expression::UnOp(Unop::Neg, expr),
+                Rule::NOT => Expression::UnOp(Unop::Not, expr),
+                Rule::deref => Expression::Deref(expr),
+                Rule::ampersand => Expression::AmpersAnd(expr),
+                rule => unreachable!("parse_expr expected prefix operator, found {:?}", rule),
+            }, None))
         })
         .map_infix(|lhs, op, rhs| {
-            let op2 = parse_binop_rule(op);
-            Expression::BinOp(Box::new(lhs), op2, Box::new(rhs))
+            let ((lhs, lop), (rhs, rop)) = (lhs?, rhs?);
+            if let Some(level) = non_associative(op.as_rule()) {
+                for other in [lop, rop].into_iter().flatten() {
+                    if non_associative(other.as_rule()) == Some(level) {
+                        return Err(ParseError::ChainedOperators(other.as_str().to_string(), op.as_str().to_string()))
+                    }
+                }
+            }
+            let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+            let expr = match op.as_rule() {
+                Rule::RANGE => Expression::Range{start: lhs, end: rhs, inclusive: false},
+                Rule::RANGE_INCLUSIVE => Expression::Range{start: lhs, end: rhs, inclusive: true},
+                _ => Expression::BinOp(lhs, parse_binop_rule(op.clone()), rhs),
+            };
+            Ok((expr, non_associative(op.as_rule()).map(|_| op)))
+        })
+        .map_postfix(|expr, op| {
+            let expr = Box::new(expr?.0);
+            let rule = op.as_rule();
+            let mut rules = op.into_inner();
+            match rule {
+                Rule::index => Ok((Expression::Index(expr, Box::new(parse_expr(rules.next().unwrap().into_inner())?)), None)),
+                Rule::field => Ok((Expression::Field(expr, Identifier::from(rules.next().unwrap().as_str())), None)),
+                _ => {
+                    let method = Identifier::from(rules.next().unwrap().as_str());
+                    let args = rules.map(|rule| parse_expr(rule.into_inner())).collect::<Result<_, _>>()?;
+                    Ok((Expression::MethodCall(expr, method, args), None))
+                }
+            }
         })
         .parse(pairs)
 }
 
-fn parse_block(pairs: &mut Pairs<Rule>) -> Result<Vec<Instruction>, ParseError> {
-    let first_rule = pairs.next().unwrap();
-    let mut res = vec![];
-    match first_rule.as_rule() {
-        Rule::empty_block => { },
-        Rule::non_empty_block => {
+fn parse_compound_assign(rule: &Pair<Rule>) -> Binop {
+    match rule.as_rule() {
+        Rule::ADD_ASSIGN => Binop::Add,
+        Rule::SUBTRACT_ASSIGN => Binop::Sub,
+        Rule::MULTIPLY_ASSIGN => Binop::Mul,
+        Rule::DIVIDE_ASSIGN => Binop::Div,
+        Rule::MODULO_ASSIGN => Binop::Mod,
+        Rule::BITAND_ASSIGN => Binop::BitAnd,
+        Rule::BITOR_ASSIGN => Binop::BitOr,
+        Rule::BITXOR_ASSIGN => Binop::BitXor,
+        Rule::SHL_ASSIGN => Binop::Shl,
+        Rule::SHR_ASSIGN => Binop::Shr,
+        _ => unreachable!("parse_compound_assign expected an assignment operator, found {:?}", rule)
+    }
+}
+
+pub fn parse_type(pair: Pair<Rule>) -> Result<Type, ParseError> {
+    let first_rule = pair.into_inner().next().unwrap();
+    Ok(match first_rule.as_rule() {
+        Rule::int_type => Type::Int(IntType::from_name(first_rule.as_str()).unwrap()),
+        Rule::bool_type => Type::Bool,
+        Rule::char_type => Type::Char,
+        Rule::str_type => Type::Str,
+        Rule::string_type => Type::String,
+        Rule::unit => Type::Unit,
+        // `Ptr` ne dit pas le type de sa cible, `&T` et `&mut T` le disent
+        Rule::ptr_type => match first_rule.into_inner().find(|rule| rule.as_rule() == Rule::type_expr) {
+            Some(rule) => Type::PointerTo(Box::new(parse_type(rule)?)),
+            None => Type::Pointer,
+        },
+        Rule::tuple_type => Type::Tuple(first_rule.into_inner().map(parse_type).collect::<Result<_, _>>()?),
+        Rule::array_type => {
             let mut rules = first_rule.into_inner();
-            while let Some(rule) = rules.next(){
-                if rule.as_rule() == Rule::instr {
-                    let instr = parse_instr(&mut rule.into_inner())?;
-                    res.push(instr)
-                }
-            }
+            let r#type = parse_type(rules.next().unwrap())?;
+            Type::Array(Box::new(r#type), parse_integer(rules.next().unwrap().as_str())?.0 as usize)
         },
-        _ => unreachable!("parse_block expected instrs, found {:?}", first_rule),
-    };
-    Ok(res)
+        Rule::vec_type => Type::VecOf(Box::new(parse_type(first_rule.into_inner().next().unwrap())?)),
+        Rule::identifier => Type::Named(Identifier::from(first_rule.as_str())),
+        _ => unreachable!("parse_type expected type, found {:?}", first_rule),
+    })
+}
+
+pub fn parse_pattern(pair: Pair<Rule>) -> Result<Pattern, ParseError> {
+    let first_rule = pair.into_inner().next().unwrap();
+    Ok(match first_rule.as_rule() {
+        Rule::identifier => Pattern::Identifier { id: Identifier::from(first_rule.as_str()), mutable: false },
+        Rule::mut_binding => Pattern::Identifier { id: Identifier::from(first_rule.into_inner().as_str()), mutable: true },
+        Rule::wildcard => Pattern::Wildcard,
+        Rule::literal_pattern => Pattern::Literal(parse_literal_pattern(first_rule.into_inner().next().unwrap())?),
+        Rule::range_pattern => {
+            let text = first_rule.as_str().to_string();
+            let mut rules = first_rule.into_inner();
+            let start = parse_literal_pattern(rules.next().unwrap().into_inner().next().unwrap())?;
+            let inclusive = rules.next().unwrap().as_rule() == Rule::RANGE_INCLUSIVE;
+            let end = parse_literal_pattern(rules.next().unwrap().into_inner().next().unwrap())?;
+            // comme rustc, un intervalle vide ou de bornes de types différents est refusé
+            let valid = match (&start, &end) {
+                (ParsedValue::Integer(a, _), ParsedValue::Integer(b, _)) => if inclusive { a <= b } else { a < b },
+                (ParsedValue::Char(a), ParsedValue::Char(b)) => if inclusive { a <= b } else { a < b },
+                _ => false,
+            };
+            if !valid { return Err(ParseError::InvalidRangePattern(text)) }
+            Pattern::Range { start, end, inclusive }
+        },
+        // `()` is the unit value
+        Rule::tuple_pattern if first_rule.as_str().trim_start_matches('(').trim_start().starts_with(')') => Pattern::Literal(ParsedValue::Unit),
+        Rule::tuple_pattern => Pattern::Tuple(first_rule.into_inner().map(parse_pattern).collect::<Result<_, _>>()?),
+        Rule::struct_pattern => {
+            let mut rules = first_rule.into_inner();
+            let name = Identifier::from(rules.next().unwrap().as_str());
+            parse_fields_pattern(name, rules.next().unwrap())?
+        },
+        Rule::variant_pattern => {
+            let mut rules = first_rule.into_inner();
+            let name = Identifier::from(rules.next().unwrap().as_str());
+            let variant = Identifier::from(rules.next().unwrap().as_str());
+            let fields = match rules.next() {
+                Some(rule) if rule.as_rule() == Rule::tuple_pattern =>
+                    Some(Box::new(Pattern::Tuple(rule.into_inner().map(parse_pattern).collect::<Result<_, _>>()?))),
+                Some(rule) => Some(Box::new(parse_fields_pattern(variant.clone(), rule)?)),
+                None => None,
+            };
+            Pattern::Variant(name, variant, fields)
+        },
+        _ => unreachable!("parse_pattern expected pattern, found {:?}", first_rule),
+    })
+}
+
+/// an integer, which may be negative, a boolean, a character or a string in a pattern
+fn parse_literal_pattern(pair: Pair<Rule>) -> Result<ParsedValue, ParseError> {
+    match pair.as_rule() {
+        Rule::int_pattern => {
+            let literal = pair.as_str();
+            let (i, suffix) = parse_integer(literal.trim_start_matches('-'))?;
+            Ok(ParsedValue::Integer(if literal.starts_with('-') { -i } else { i }, suffix))
+        },
+        _ => match parse_expr(Pairs::single(pair))? {
+            Expression::Const(v) => Ok(v),
+            _ => unreachable!("a literal pattern is a constant"),
+        },
+    }
+}
+
+/// `f: e` or `f`, short for `f: f`, in a struct or variant literal
+fn parse_field_inits(pairs: Pairs<Rule>) -> Result<Vec<(Identifier, Expression)>, ParseError> {
+    pairs.map(|rule| {
+        let mut rules = rule.into_inner();
+        let id = Identifier::from(rules.next().unwrap().as_str());
+        let expr = match rules.next() {
+            Some(rule) => parse_expr(rule.into_inner())?,
+            None => Expression::Identifier(id.clone()),
+        };
+        Ok((id, expr))
+    }).collect()
+}
+
+/// `{ f: p, g, .. }` in a struct or variant pattern
+fn parse_fields_pattern(name: Identifier, pair: Pair<Rule>) -> Result<Pattern, ParseError> {
+    let mut fields = vec![];
+    let mut rest = false;
+    for rule in pair.into_inner() {
+        let mut rules = rule.into_inner();
+        let rule = rules.next().unwrap();
+        match rule.as_rule() {
+            Rule::rest => rest = true,
+            // `mut f` et `f` sont des raccourcis pour `f: mut f` et `f: f`
+            Rule::mut_binding => {
+                let id = Identifier::from(rule.into_inner().as_str());
+                fields.push((id.clone(), Pattern::Identifier { id, mutable: true }));
+            },
+            _ => {
+                let id = Identifier::from(rule.as_str());
+                let pattern = match rules.next() {
+                    Some(rule) => parse_pattern(rule)?,
+                    None => Pattern::Identifier { id: id.clone(), mutable: false },
+                };
+                fields.push((id, pattern));
+            },
+        }
+    }
+    Ok(Pattern::Struct { name, fields, rest })
+}
+
+/// `field: Type` in a struct or a variant
+fn parse_field_def(pair: Pair<Rule>) -> Result<(Identifier, Type), ParseError> {
+    let mut rules = pair.into_inner();
+    Ok((Identifier::from(rules.next().unwrap().as_str()), parse_type(rules.next().unwrap())?))
+}
+
+fn parse_fn_def(pairs: &mut Pairs<Rule>) -> Result<Function, ParseError> {
+    let name = Identifier::from(pairs.next().unwrap().as_str());
+    let mut params = vec![];
+    for rule in pairs.next().unwrap().into_inner() {
+        let mutable = rule.as_rule() == Rule::mut_param;
+        let mut rules = rule.into_inner();
+        let id = Identifier::from(rules.next().unwrap().as_str());
+        let r#type = parse_type(rules.next().unwrap())?;
+        params.push(Param { id, mutable, r#type });
+    }
+    let mut rule = pairs.next().unwrap();
+    let mut ret = Type::Unit;
+    if rule.as_rule() == Rule::type_expr {
+        ret = parse_type(rule)?;
+        rule = pairs.next().unwrap();
+    }
+    let body = Box::new(parse_block(rule)?);
+    Ok(Function { name, params, ret, body })
+}
+
+/// parses `{ ... }`, the last expression not followed by `;` is the value of the block
+fn parse_block(pair: Pair<Rule>) -> Result<Expression, ParseError> {
+    let (instrs, tail) = parse_items(pair.into_inner())?;
+    Ok(Expression::Block(instrs, tail))
+}
+
+/// parses the `block_item`s of a block or a program, returning the statements
+/// and the final expression if it is not followed by `;`
+pub fn parse_items(pairs: Pairs<Rule>) -> Result<(Vec<Instruction>, Option<Box<Expression>>), ParseError> {
+    let mut instrs = vec![];
+    let mut tail = None;
+    let mut items = pairs.filter(|rule| rule.as_rule() == Rule::block_item).peekable();
+    while let Some(item) = items.next() {
+        let mut rules = item.into_inner();
+        let rule = rules.next().unwrap();
+        let terminated = rules.next().is_some();
+        let block_like = rule.as_rule() != Rule::instr;
+        let instr = match rule.as_rule() {
+            Rule::instr => parse_instr(&mut rule.into_inner())?,
+            Rule::if_expr | Rule::match_expr | Rule::instrs | Rule::loop_expr => Instruction::Expr(parse_expr(Pairs::single(rule))?),
+            _ => parse_instr(&mut Pairs::single(rule))?,
+        };
+        let last = items.peek().is_none();
+        match instr {
+            Instruction::Expr(expr) if !terminated && last => tail = Some(Box::new(expr)),
+            // only block-like instructions may omit the `;` in the middle of a block
+            instr if !terminated && !last && !block_like => return Err(ParseError::MissingSemicolon(instr.to_string())),
+            instr => instrs.push(instr),
+        }
+    }
+    Ok((instrs, tail))
 }
 
 pub fn parse_instr(pairs: &mut Pairs<Rule>) -> Result<Instruction, ParseError> {
 //    println!("{}\n{:?}\n\n", pairs.as_str(), pairs);
     let first_rule = pairs.next().unwrap();
     match first_rule.as_rule() {
-        Rule::expr => Ok(Instruction::Expr(parse_expr(first_rule.into_inner()))),
+        Rule::expr => Ok(Instruction::Expr(parse_expr(first_rule.into_inner())?)),
         Rule::let_equals => {
             let mut rules = first_rule.into_inner();
-            let id = rules.next().unwrap().as_span().as_str().to_string();
-            let id = Identifier::from(id.as_str());
-            let expr = rules.next().unwrap().into_inner();
-            Ok(Instruction::Let{id, mutable: false, expr: parse_expr(expr) })
-        },
-        Rule::let_mut_equals => {
-            let mut rules = first_rule.into_inner();
-            let id = rules.next().unwrap().as_span().as_str().to_string();
-            let id = Identifier::from(id.as_str());
-            let expr = rules.next().unwrap().into_inner();
-            Ok(Instruction::Let{id, mutable: true, expr: parse_expr(expr) })
+            let pattern = parse_pattern(rules.next().unwrap())?;
+            let r#type = match rules.peek() {
+                Some(rule) if rule.as_rule() == Rule::type_expr => Some(parse_type(rules.next().unwrap())?),
+                _ => None,
+            };
+            let expr = parse_expr(rules.next().unwrap().into_inner())?;
+            Ok(Instruction::Let{pattern, r#type, expr})
         },
         Rule::write_at => {
             let mut rules = first_rule.into_inner();
-            let lexpr = parse_expr(rules.next().unwrap().into_inner());
-            let expr = parse_expr(rules.next().unwrap().into_inner());
-            Ok(Instruction::WriteAt(lexpr, expr))
-        },
-        Rule::if_instr => {
-            let mut rules = first_rule.into_inner();
-            let cond = parse_expr(rules.next().unwrap().into_inner());
-            let cond_true = Box::new(Instruction::Block(parse_block(&mut rules.next().unwrap().into_inner())?));
-            let cond_false = Box::new(Instruction::Block(parse_block(&mut rules.next().unwrap().into_inner())?));
-            Ok(Instruction::IfElse{cond, cond_true, cond_false})
+            let lexpr = parse_expr(rules.next().unwrap().into_inner())?;
+            let mut rule = rules.next().unwrap();
+            let mut op = None;
+            if rule.as_rule() != Rule::expr {
+                op = Some(parse_compound_assign(&rule));
+                rule = rules.next().unwrap();
+            }
+            let expr = parse_expr(rule.into_inner())?;
+            Ok(Instruction::WriteAt(lexpr, op, expr))
         },
         Rule::while_instr => {
-            let mut rules = first_rule.into_inner();
-            let cond = parse_expr(rules.next().unwrap().into_inner());
-            let instr = Instruction::Block(parse_block(&mut rules.next().unwrap().into_inner())?);
-            Ok(Instruction::While(cond, Box::new(instr)))
-        },
-        Rule::instrs => {
-            Ok(Instruction::Block(parse_block(&mut first_rule.into_inner())?))
+            let mut rules = first_rule.into_inner().peekable();
+            let label = rules.next_if(|rule| rule.as_rule() == Rule::label).map(|rule| Identifier::from(rule.as_str()));
+            let cond = parse_expr(rules.next().unwrap().into_inner())?;
+            let body = Box::new(parse_block(rules.next().unwrap())?);
+            Ok(Instruction::While{label, cond, body})
         },
         Rule::free_instr => {
-            let lexpr = parse_expr(first_rule.into_inner());
+            let lexpr = parse_expr(first_rule.into_inner())?;
             Ok(Instruction::Free(lexpr))
         },
+        Rule::break_instr => {
+            let mut rules = first_rule.into_inner().peekable();
+            let label = rules.next_if(|rule| rule.as_rule() == Rule::label).map(|rule| Identifier::from(rule.as_str()));
+            let expr = rules.next().map(|rule| parse_expr(rule.into_inner())).transpose()?;
+            Ok(Instruction::Break(label, expr))
+        },
+        Rule::continue_instr => {
+            let label = first_rule.into_inner().next().map(|rule| Identifier::from(rule.as_str()));
+            Ok(Instruction::Continue(label))
+        },
+        Rule::return_instr => {
+            let expr = first_rule.into_inner().next().map(|rule| parse_expr(rule.into_inner())).transpose()?;
+            Ok(Instruction::Return(expr))
+        },
+        Rule::for_instr => {
+            let mut rules = first_rule.into_inner().peekable();
+            let label = rules.next_if(|rule| rule.as_rule() == Rule::label).map(|rule| Identifier::from(rule.as_str()));
+            let id = Identifier::from(rules.next().unwrap().as_str());
+            let iter = parse_expr(rules.next().unwrap().into_inner())?;
+            let body = Box::new(parse_block(rules.next().unwrap())?);
+            Ok(Instruction::For{label, id, iter, body})
+        },
+        Rule::fn_def => {
+            let fun = parse_fn_def(&mut first_rule.into_inner())?;
+            Ok(Instruction::FnDef(Rc::new(fun)))
+        },
+        Rule::struct_def => {
+            let mut rules = first_rule.into_inner();
+            let name = Identifier::from(rules.next().unwrap().as_str());
+            let fields = rules.map(parse_field_def).collect::<Result<_, _>>()?;
+            Ok(Instruction::StructDef(Rc::new(Struct { name, fields })))
+        },
+        Rule::enum_def => {
+            let mut rules = first_rule.into_inner();
+            let name = Identifier::from(rules.next().unwrap().as_str());
+            let variants = rules.map(|rule| {
+                let mut rules = rule.into_inner();
+                let name = Identifier::from(rules.next().unwrap().as_str());
+                let fields = match rules.next() {
+                    Some(rule) if rule.as_rule() == Rule::tuple_fields => Fields::Tuple(rule.into_inner().map(parse_type).collect::<Result<_, _>>()?),
+                    Some(rule) => Fields::Struct(rule.into_inner().map(parse_field_def).collect::<Result<_, _>>()?),
+                    None => Fields::Unit,
+                };
+                Ok(Variant { name, fields })
+            }).collect::<Result<_, _>>()?;
+            Ok(Instruction::EnumDef(Rc::new(Enum { name, variants })))
+        },
         _ => unreachable!("parse_instr expected instr, found {:?}", first_rule),
     }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+use std::fmt;
+
+use crate::r#type::IntType;
+
+/// range of integers of type `r#type`, stored as an arithmetic progression so that
+/// `rev()` and `step_by()` can be chained in any order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    first: i128,
+    step: i128,
+    len: u128,
+    r#type: IntType,
+}
+
+impl Range {
+    /// `start..end`, or `start..=end` when `inclusive`
+    pub fn new(start: i128, end: i128, inclusive: bool, r#type: IntType) -> Self {
+        let end = if inclusive { end + 1 } else { end };
+        let len = (end - start).max(0) as u128;
+        Range { first: start, step: 1, len, r#type }
+    }
+
+    /// type of the elements, that of the bounds
+    pub fn int_type(&self) -> IntType {
+        self.r#type
+    }
+
+    fn last(&self) -> i128 {
+        self.nth(self.len - 1)
+    }
+
+    fn nth(&self, n: u128) -> i128 {
+        self.first + self.step * n as i128
+    }
+
+    pub fn rev(&self) -> Self {
+        if self.len == 0 { return *self }
+        Range { first: self.last(), step: -self.step, ..*self }
+    }
+
+    /// panics if `n` is 0, the caller must check it
+    pub fn step_by(&self, n: usize) -> Self {
+        assert!(n != 0);
+        let n = n as u128;
+        if n >= self.len { return Range { len: self.len.min(1), ..*self } }
+        Range { step: self.step * n as i128, len: self.len.div_ceil(n), ..*self }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i128> + '_ {
+        (0..self.len).map(|n| self.nth(n))
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.len == 0 { return write!(f, "{}..{}", self.first, self.first) }
+        let (low, high) = if self.step > 0 { (self.first, self.last()) } else { (self.last(), self.first) };
+        let range = format!("{}..{}", low, high + 1);
+        match self.step {
+            1 => write!(f, "{}", range),
+            -1 => write!(f, "({}).rev()", range),
+            s if s > 0 => write!(f, "({}).step_by({})", range, s),
+            s => write!(f, "({}).rev().step_by({})", range, -s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_range {
+    use super::*;
+
+    fn collect(r: Range) -> Vec<i128> {
+        r.iter().collect()
+    }
+
+    fn range(start: i128, end: i128, inclusive: bool) -> Range {
+        Range::new(start, end, inclusive, IntType::Isize)
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(collect(range(2, 5, false)), vec![2, 3, 4]);
+        assert_eq!(collect(range(2, 5, true)), vec![2, 3, 4, 5]);
+        assert_eq!(collect(range(5, 2, false)), vec![]);
+        assert_eq!(collect(range(5, 5, true)), vec![5]);
+    }
+
+    /// `rev` and `step_by` in any order give the same elements as the iterators of Rust
+    #[test]
+    fn test_rev_step_by() {
+        let std = |it: &mut dyn Iterator<Item = isize>| it.map(|i| i as i128).collect::<Vec<_>>();
+        for (start, end) in [(0, 10), (-7, 8), (3, 4), (4, 3)] {
+            for n in 1..12 {
+                let r = range(start as i128, end as i128, false);
+                assert_eq!(collect(r.step_by(n)), std(&mut (start..end).step_by(n)));
+                assert_eq!(collect(r.rev()), std(&mut (start..end).rev()));
+                assert_eq!(collect(r.step_by(n).rev()), std(&mut (start..end).step_by(n).rev()));
+                assert_eq!(collect(r.rev().step_by(n)), std(&mut (start..end).rev().step_by(n)));
+                assert_eq!(collect(r.rev().step_by(n).rev()), std(&mut (start..end).rev().step_by(n).rev()));
+            }
+        }
+    }
+
+    /// la longueur de `isize::MIN..=isize::MAX` ne tient pas dans un `usize`
+    #[test]
+    fn test_bounds() {
+        let (min, max) = (isize::MIN as i128, isize::MAX as i128);
+        let r = range(min, max, true);
+        assert_eq!(r.iter().take(2).collect::<Vec<_>>(), vec![min, min + 1]);
+        assert_eq!(r.rev().iter().take(2).collect::<Vec<_>>(), vec![max, max - 1]);
+        assert_eq!(collect(r.step_by(usize::MAX)), vec![min, max]);
+        assert_eq!(collect(r.rev().step_by(usize::MAX)), vec![max, min]);
+        let max = u64::MAX as i128;
+        let r = Range::new(max - 1, max, true, IntType::U64);
+        assert_eq!(collect(r), vec![max - 1, max]);
+        assert_eq!(collect(r.rev()), vec![max, max - 1]);
+        assert_eq!(r.int_type(), IntType::U64);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(range(0, 5, true).to_string(), "0..6");
+        assert_eq!(range(0, 5, false).rev().to_string(), "(0..5).rev()");
+        assert_eq!(range(0, 10, false).step_by(3).to_string(), "(0..10).step_by(3)");
+        assert_eq!(range(0, 10, false).step_by(3).rev().to_string(), "(0..10).rev().step_by(3)");
+    }
+}
@@ -0,0 +1,219 @@
+use std::path::PathBuf;
+use rustyline::{Context, Editor, Helper};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use crate::identifier::Identifier;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::expression::Expression;
+use crate::parsing::program::Program;
+use crate::namespace::NameSpace;
+use crate::value::Value;
+use crate::parser::Parse;
+use crate::error::Error;
+use crate::memory::Memory;
+use crate::r#type::Type;
+
+// PROMPTS
+const PROMPT: &str = "µRust # ";
+const CONTINUATION_PROMPT: &str = "   ... ";
+
+// fichier d'historique, dans le répertoire de l'utilisateur
+const HISTORY_FILE: &str = ".microrust_history";
+
+// mots-clés de grammar.pest
+const KEYWORDS: [&str; 20] = ["let", "mut", "if", "else", "while", "true", "false", "Ptr", "new", "free",
+    "fn", "break", "continue", "return", "loop", "for", "in", "struct", "enum", "match"];
+
+/// runs an instruction and prints its value
+fn parse_exec(input: &str, nss: &mut Memory) -> Result<(), Error> {
+    let instr = Instruction::parse(input)?;
+    for warning in instr.check_types(nss)? { println!("warning: {}", warning) }
+    match instr {
+        // afficher une variable ne la déplace pas
+        Instruction::Expr(e) => {
+            let val = e.eval_borrow(nss).map_err(|cf| Error::EvalError(cf.into()))?;
+            println!("- : {} = {}", Type::from(&val), nss.show(&val));
+            e.drop_temporary(nss, &val);
+            Ok(())
+        }
+        instr => {
+            let (id, val) = instr.exec(nss).map_err(|cf| Error::EvalError(cf.into()))?;
+            println!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), nss.show(&val));
+            Ok(())
+        }
+    }
+}
+
+/// true if `input` cannot be a whole instruction yet: an unclosed delimiter, string
+/// or block comment, or a trailing operator waiting for its right operand
+fn incomplete(input: &str) -> bool {
+    let mut depth = 0isize;
+    let mut comments = 0usize;
+    let mut string = false;
+    let mut last = None;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('/', Some('*')) => { chars.next(); comments += 1 },
+            ('*', Some('/')) if comments > 0 => { chars.next(); comments -= 1 },
+            _ if comments > 0 => (),
+            ('/', Some('/')) => { chars.find(|c| *c == '\n'); },
+            ('"', _) => {
+                string = true;
+                while let Some(c) = chars.next() {
+                    if c == '\\' { chars.next(); } else if c == '"' { string = false; break }
+                }
+                last = Some(c)
+            },
+            // un caractère, à distinguer d'une étiquette `'a`
+            ('\'', Some('\\')) => { chars.find(|c| *c == '\''); last = Some(c) },
+            ('\'', Some(_)) if chars.clone().nth(1) == Some('\'') => { chars.nth(1); last = Some(c) },
+            ('(' | '{' | '[', _) => { depth += 1; last = Some(c) },
+            (')' | '}' | ']', _) => { depth -= 1; last = Some(c) },
+            _ if c.is_whitespace() => (),
+            _ => last = Some(c),
+        }
+    }
+    // trop de délimiteurs fermants : c'est une erreur, pas une instruction incomplète
+    string || comments > 0 || depth > 0
+        || last.is_some_and(|c| "+-*/%=<>&|^!,.:".contains(c))
+}
+
+fn new_memory(max_depth: usize) -> Memory {
+    let mut nss = Memory::new();
+    nss.set_max_depth(max_depth);
+    nss.push(NameSpace::new());
+    nss
+}
+
+// COMMANDES DE L'INTERPRÉTEUR
+const COMMANDS: [&str; 6] = [":type", ":env", ":heap", ":reset", ":load", ":quit"];
+
+/// runs a `:command`, returns false to leave the REPL
+fn command(line: &str, nss: &mut Memory, max_depth: usize) -> bool {
+    let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let arg = arg.trim();
+    match (command, arg) {
+        (":type", "") => println!("usage: :type expr"),
+        // le type est inféré sans évaluer l'expression : ni effet de bord, ni calcul
+        (":type", input) => match Expression::parse(input) {
+            Ok(expr) => match expr.type_of(nss) {
+                Ok(Some(t)) => println!("{} : {}", expr, t),
+                // connu seulement à l'exécution, comme celui d'un élément de `Vec`
+                Ok(None) => println!("{} : _", expr),
+                Err(e) => println!("{}", e),
+            },
+            Err(e) => println!("{}", Error::ParseError(e)),
+        },
+        (":env", "") => print!("{}", nss.stack()),
+        (":heap", "") => print!("{}", nss.heap()),
+        (":reset", "") => *nss = new_memory(max_depth),
+        (":load", "") => println!("usage: :load file"),
+        (":load", path) => match std::fs::read_to_string(path) {
+            Ok(input) => match Program::parse(&input) {
+                Ok(program) => match program.check_types(nss) {
+                    Ok(warnings) => {
+                        for warning in warnings { println!("warning: {}", warning) }
+                        match program.exec(nss) {
+                            Ok(Value::Unit) => (),
+                            Ok(val) => {
+                                println!("- : {} = {}", Type::from(&val), nss.show(&val));
+                                nss.drop_value(&val);
+                            },
+                            Err(cf) => println!("{}", Error::EvalError(cf.into())),
+                        }
+                    },
+                    Err(e) => println!("{}", e),
+                },
+                Err(e) => println!("{}", Error::ParseError(e)),
+            },
+            Err(e) => println!("cannot read `{}`: {}", path, e),
+        },
+        (":quit", "") => return false,
+        _ if COMMANDS.contains(&command) => println!("{} takes no argument", command),
+        _ => println!("unknown command `{}`, expected one of {}", command, COMMANDS.join(", ")),
+    }
+    true
+}
+
+// COMPLÉTION
+/// completes keywords, bound identifiers and, at the start of the line, `:commands`
+struct ReplHelper {
+    identifiers: Vec<Identifier>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos].rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == ':')).map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        let identifiers = self.identifiers.iter().map(|id| id.to_string());
+        let candidates: Vec<String> = if word.starts_with(':') {
+            if start > 0 { return Ok((start, vec![])) }
+            COMMANDS.iter().map(|c| c.to_string()).collect()
+        } else {
+            KEYWORDS.iter().map(|k| k.to_string()).chain(identifiers).collect()
+        };
+        let mut matches = candidates.into_iter()
+            .filter(|c| c.starts_with(word))
+            .map(|c| Pair { display: c.clone(), replacement: c })
+            .collect::<Vec<_>>();
+        matches.sort_by(|a, b| a.display.cmp(&b.display));
+        matches.dedup_by(|a, b| a.display == b.display);
+        Ok((start, matches))
+    }
+}
+
+impl Hinter for ReplHelper { type Hint = String; }
+impl Highlighter for ReplHelper {}
+impl Validator for ReplHelper {}
+impl Helper for ReplHelper {}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+// BOUCLE D'INTERACTION
+pub fn repl(max_depth: usize) {
+    let mut editor: Editor<ReplHelper, FileHistory> = Editor::new().unwrap();
+    editor.set_helper(Some(ReplHelper { identifiers: vec![] }));
+    let history = history_path();
+    // pas d'historique au premier lancement
+    if let Some(path) = &history { let _ = editor.load_history(path); }
+    let mut nss = new_memory(max_depth);
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+        if let Some(helper) = editor.helper_mut() { helper.identifiers = nss.identifiers() }
+        match editor.readline(prompt) {
+            Ok(line) => {
+                // une ligne vide abandonne l'instruction en cours
+                if line.trim().is_empty() { input.clear(); continue }
+                if input.is_empty() && line.trim_start().starts_with(':') {
+                    let _ = editor.add_history_entry(line.trim());
+                    if command(line.trim(), &mut nss, max_depth) { continue } else { break }
+                }
+                input.push_str(&line);
+                input.push('\n');
+            },
+            // Ctrl-C abandonne l'instruction en cours sans toucher à la mémoire
+            Err(ReadlineError::Interrupted) => { input.clear(); continue },
+            Err(ReadlineError::Eof) => break,
+            Err(e) => { eprintln!("{}", e); break },
+        }
+        if incomplete(&input) { continue }
+        let _ = editor.add_history_entry(input.trim_end());
+        if let Err(e) = parse_exec(&input, &mut nss) {
+            println!("{}", e);
+        }
+        input.clear();
+    }
+    if let Some(path) = &history {
+        if let Err(e) = editor.save_history(path) { eprintln!("cannot save the history: {}", e) }
+    }
+}
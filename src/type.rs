@@ -1,22 +1,189 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::identifier::Identifier;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
-    Int,
+    Int(IntType),
     Bool,
     Unit,
     Pointer,
+    /// a range of integers of the given type
+    Range(IntType),
+    Char,
+    Str,
+    String,
+    /// `Vec<T>`, the type of the elements is not tracked
+    Vec,
+    /// `&T` or `&mut T` in an annotation, a pointer to a value of type `T`
+    PointerTo(Box<Type>),
+    /// `Vec<T>` in an annotation
+    VecOf(Box<Type>),
+    Tuple(Vec<Type>),
+    /// `[T; n]`
+    Array(Box<Type>, usize),
+    /// a struct or an enum
+    Named(Identifier),
 }
 
 use std::fmt::{self, Display};
 
+impl Type {
+    /// the type of the values of an annotation, whose pointer targets and `Vec` elements
+    /// are not tracked
+    pub fn erased(&self) -> Type {
+        match self {
+            Type::PointerTo(_) => Type::Pointer,
+            Type::VecOf(_) => Type::Vec,
+            Type::Tuple(ts) => Type::Tuple(ts.iter().map(Type::erased).collect()),
+            Type::Array(t, n) => Type::Array(Box::new(t.erased()), *n),
+            t => t.clone(),
+        }
+    }
+}
+
 impl Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Type::*;
         match self {
-            Int => write!(f, "isize"),
+            Int(t) => write!(f, "{}", t),
             Bool => write!(f, "bool"),
             Unit => write!(f, "unit"),
-            Pointer => write!(f, "pointer")
+            Pointer => write!(f, "pointer"),
+            Range(t) => write!(f, "Range<{}>", t),
+            Char => write!(f, "char"),
+            Str => write!(f, "&str"),
+            String => write!(f, "String"),
+            Vec => write!(f, "Vec<_>"),
+            PointerTo(t) => write!(f, "&{}", t),
+            VecOf(t) => write!(f, "Vec<{}>", t),
+            Tuple(types) if types.len() == 1 => write!(f, "({},)", types[0]),
+            Array(t, n) => write!(f, "[{}; {}]", t, n),
+            Named(name) => write!(f, "{}", name),
+            Tuple(types) => write!(f, "({})", types.iter().map(|t| t.to_string()).collect::<std::vec::Vec<_>>().join(", ")),
         }
     }
 }
 
+
+/// width and signedness of an integer, `isize` and `usize` are 64 bits wide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntType {
+    I8,
+    I16,
+    I32,
+    I64,
+    #[default]
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+}
+
+impl IntType {
+    pub const ALL: [IntType; 10] = [IntType::I8, IntType::I16, IntType::I32, IntType::I64, IntType::Isize,
+        IntType::U8, IntType::U16, IntType::U32, IntType::U64, IntType::Usize];
+
+    /// the type named `name`, as in a type or a literal suffix
+    pub fn from_name(name: &str) -> Option<IntType> {
+        IntType::ALL.into_iter().find(|t| t.to_string() == name)
+    }
+
+    pub fn bits(&self) -> u32 {
+        use IntType::*;
+        match self {
+            I8 | U8 => 8,
+            I16 | U16 => 16,
+            I32 | U32 => 32,
+            I64 | U64 => 64,
+            Isize | Usize => isize::BITS,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64 | IntType::Isize)
+    }
+
+    pub fn min(&self) -> i128 {
+        if self.is_signed() { -(1 << (self.bits() - 1)) } else { 0 }
+    }
+
+    pub fn max(&self) -> i128 {
+        if self.is_signed() { (1 << (self.bits() - 1)) - 1 } else { (1 << self.bits()) - 1 }
+    }
+
+    pub fn contains(&self, i: i128) -> bool {
+        self.min() <= i && i <= self.max()
+    }
+
+    /// `i` modulo 2 to the number of bits, as the `wrapping_*` methods compute it
+    pub fn wrap(&self, i: i128) -> i128 {
+        let modulus = 1i128 << self.bits();
+        let i = i.rem_euclid(modulus);
+        if i > self.max() { i - modulus } else { i }
+    }
+
+    /// `i` clamped to the bounds of the type, as the `saturating_*` methods compute it
+    pub fn saturate(&self, i: i128) -> i128 {
+        i.clamp(self.min(), self.max())
+    }
+}
+
+impl Display for IntType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IntType::*;
+        write!(f, "{}", match self {
+            I8 => "i8",
+            I16 => "i16",
+            I32 => "i32",
+            I64 => "i64",
+            Isize => "isize",
+            U8 => "u8",
+            U16 => "u16",
+            U32 => "u32",
+            U64 => "u64",
+            Usize => "usize",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_int_type {
+    use super::*;
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!((IntType::I8.min(), IntType::I8.max()), (-128, 127));
+        assert_eq!((IntType::U8.min(), IntType::U8.max()), (0, 255));
+        assert_eq!((IntType::I64.min(), IntType::U64.max()), (i64::MIN as i128, u64::MAX as i128));
+        assert_eq!((IntType::Isize.max(), IntType::Usize.max()), (isize::MAX as i128, usize::MAX as i128));
+        assert!(IntType::U8.contains(255) && !IntType::U8.contains(256) && !IntType::U8.contains(-1));
+        assert!(IntType::I8.contains(-128) && !IntType::I8.contains(128));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(IntType::U8.wrap(256), 0);
+        assert_eq!(IntType::U8.wrap(-1), 255);
+        assert_eq!(IntType::I8.wrap(128), -128);
+        assert_eq!(IntType::I8.wrap(200), -56);
+        assert_eq!(IntType::I64.wrap(-(i64::MIN as i128)), i64::MIN as i128);
+        assert_eq!(IntType::U16.wrap(70000), 70000 - 65536);
+    }
+
+    #[test]
+    fn test_saturate() {
+        assert_eq!(IntType::U8.saturate(300), 255);
+        assert_eq!(IntType::U8.saturate(-5), 0);
+        assert_eq!(IntType::I8.saturate(-200), -128);
+        assert_eq!(IntType::I32.saturate(7), 7);
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(IntType::from_name("u8"), Some(IntType::U8));
+        assert_eq!(IntType::from_name("isize"), Some(IntType::Isize));
+        assert_eq!(IntType::from_name("u128"), None);
+        assert!(IntType::ALL.iter().all(|t| IntType::from_name(&t.to_string()) == Some(*t)));
+    }
+}
@@ -0,0 +1,770 @@
+// VÉRIFICATION STATIQUE DES TYPES
+// les types sont inférés comme l'évaluation les calcule : une variable garde le type de
+// sa première valeur, un littéral entier sans suffixe prend le type attendu. Un type
+// inconnu (la cible d'un pointeur, l'élément d'un `Vec`) n'est jamais une erreur :
+// seules les erreurs que l'évaluation rencontrerait à coup sûr sont signalées.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::{Error, EvalError};
+use crate::error::EvalError::TypeMismatch;
+use crate::eval::int_method;
+use crate::exhaustiveness::{check_match, UnreachableArm};
+use crate::identifier::Identifier;
+use crate::items::Items;
+use crate::memory::Memory;
+use crate::parsing::binop::Binop;
+use crate::parsing::expression::Expression;
+use crate::parsing::function::Function;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::parsedvalue::ParsedValue;
+use crate::parsing::pattern::Pattern;
+use crate::parsing::program::Program;
+use crate::parsing::structure::{Enum, Fields, Struct};
+use crate::parsing::unop::Unop;
+use crate::r#type::{IntType, Type};
+use crate::value::Value;
+
+const ISIZE: Type = Type::Int(IntType::Isize);
+const USIZE: Type = Type::Int(IntType::Usize);
+
+type Breaks = Vec<(Expression, Option<Type>)>;
+
+struct Checker<'a> {
+    mem: &'a Memory,
+    /// functions, structs and enums visible from the block being checked
+    items: Rc<Items>,
+    /// types des variables, un espace de noms par bloc, `None` si le type est inconnu
+    scopes: Vec<HashMap<Identifier, Option<Type>>>,
+    /// first scope and return type of each function being checked
+    frames: Vec<(usize, Type)>,
+    /// loops being checked, the innermost last: their label, and the values of their `break`s
+    /// with their types for a `loop`, none for a `while` or a `for`
+    loops: Vec<(Option<Identifier>, Option<Breaks>)>,
+    errors: Vec<EvalError>,
+    /// arms of a `match` that can never match
+    warnings: Vec<UnreachableArm>,
+    /// the first `match` where a value matches no arm
+    non_exhaustive: Option<EvalError>,
+}
+
+/// the operators giving `true` or `false` from two values of the same type
+fn is_comparison(op: Binop) -> bool {
+    matches!(op, Binop::Eq | Binop::Neq | Binop::Lt | Binop::Gt | Binop::Leq | Binop::Geq)
+}
+
+fn is_bitwise(op: Binop) -> bool {
+    matches!(op, Binop::BitAnd | Binop::BitOr | Binop::BitXor)
+}
+
+/// false if a value of type `found` is known not to be of the annotation `expected`:
+/// a pointer or a `Vec` whose target or elements are not tracked may be of any `&T` or `Vec<T>`
+fn conforms(found: &Type, expected: &Type) -> bool {
+    use Type::*;
+    match (found, expected) {
+        (Pointer, PointerTo(_)) | (Vec, VecOf(_)) => true,
+        (PointerTo(t1), PointerTo(t2)) | (VecOf(t1), VecOf(t2)) => conforms(t1, t2),
+        (Tuple(ts1), Tuple(ts2)) => ts1.len() == ts2.len() && ts1.iter().zip(ts2).all(|(t1, t2)| conforms(t1, t2)),
+        (Array(t1, n1), Array(t2, n2)) => n1 == n2 && conforms(t1, t2),
+        _ => found == expected,
+    }
+}
+
+/// false if comparing values of types `t1` and `t2` is an error, as `compare` decides it:
+/// structs, enums, pointers and ranges are only compared for equality
+fn comparable(t1: &Type, t2: &Type, ordered: bool) -> bool {
+    use Type::*;
+    match (t1, t2) {
+        (Int(a), Int(b)) => a == b,
+        (Bool, Bool) | (Char, Char) | (Unit, Unit) | (Vec, Vec) => true,
+        (Tuple(ts1), Tuple(ts2)) => ts1.len() == ts2.len() && ts1.iter().zip(ts2).all(|(t1, t2)| comparable(t1, t2, ordered)),
+        // deux tableaux vides sont égaux, quel que soit le type de leurs éléments
+        (Array(_, 0), Array(_, 0)) => true,
+        (Array(t1, n1), Array(t2, n2)) => n1 == n2 && comparable(t1, t2, ordered),
+        (Named(n1), Named(n2)) => n1 == n2 && !ordered,
+        (Pointer, Pointer) | (Range(_), Range(_)) => !ordered,
+        (Str | String, Str | String) => true,
+        _ => false,
+    }
+}
+
+impl<'a> Checker<'a> {
+    fn find_function(&self, id: &Identifier) -> Option<Rc<Function>> {
+        self.items.find_function(id).map(|(fun, _)| fun)
+    }
+
+    fn find_struct(&self, id: &Identifier) -> Option<Rc<Struct>> {
+        self.items.find_struct(id)
+    }
+
+    fn find_enum(&self, id: &Identifier) -> Option<Rc<Enum>> {
+        self.items.find_enum(id).or_else(|| self.mem.find_enum(id).ok())
+    }
+
+    /// a type written in a definition, unknown if it names no struct or enum,
+    /// as the `T` of the built-in `Option`
+    fn known(&self, t: &Type) -> Option<Type> {
+        match t {
+            Type::Named(name) if self.find_struct(name).is_none() && self.find_enum(name).is_none() => None,
+            Type::Tuple(ts) => ts.iter().map(|t| self.known(t)).collect::<Option<_>>().map(Type::Tuple),
+            Type::Array(t, n) => self.known(t).map(|t| Type::Array(Box::new(t), *n)),
+            t => Some(t.clone()),
+        }
+    }
+
+    fn declare(&mut self, id: &Identifier, t: Option<Type>) {
+        // une variable n'a pas le type précis d'une annotation `&T` ou `Vec<T>`
+        self.scopes.last_mut().unwrap().insert(id.clone(), t.as_ref().map(Type::erased));
+    }
+
+    /// type of a variable, those of the memory are visible outside of the functions
+    fn lookup(&self, id: &Identifier) -> Result<Option<Type>, EvalError> {
+        let base = self.frames.last().map(|(base, _)| *base).unwrap_or(0);
+        for scope in self.scopes[base..].iter().rev() {
+            if let Some(t) = scope.get(id) { return Ok(t.clone()) }
+        }
+        if !self.frames.is_empty() { return Err(EvalError::Undefined(id.clone())) }
+        let addr = self.mem.get_address(id)?;
+        Ok(self.mem.type_at(&addr).ok().flatten())
+    }
+
+    fn mismatch(&mut self, e: &Expression, expected: Type, found: Type) {
+        self.errors.push(TypeMismatch { expression: Some(e.clone().into()), expected, found: Some(found) });
+    }
+
+    fn expect(&mut self, e: &Expression, found: Option<Type>, expected: &Type) {
+        if let Some(found) = found.filter(|t| !conforms(t, expected)) { self.mismatch(e, expected.clone(), found) }
+    }
+
+    /// an integer of any type, as an index, a length or a bound of a range
+    fn expect_int(&mut self, e: &Expression) {
+        if let Some(t) = self.expr(e).filter(|t| !matches!(t, Type::Int(_))) { self.mismatch(e, ISIZE, t) }
+    }
+
+    fn args_count(&mut self, function: &Identifier, args: &[Expression], expected: usize) -> bool {
+        if args.len() != expected {
+            self.errors.push(EvalError::WrongArgumentCount { function: function.clone(), expected, found: args.len() });
+        }
+        args.len() == expected
+    }
+
+    /// les définitions d'un bloc sont visibles dans tout le bloc
+    fn items(&mut self, instrs: &[Instruction], tail: Option<&Expression>) -> Option<Type> {
+        let items = Items::block(&self.items, instrs);
+        let outer = std::mem::replace(&mut self.items, items);
+        instrs.iter().for_each(|instr| self.instr(instr));
+        // un bloc qui finit par `return`, `break` ou `continue` ne donne pas de valeur
+        let diverges = matches!(instrs.last(), Some(Instruction::Return(_) | Instruction::Break(..) | Instruction::Continue(_)));
+        let t = tail.map_or((!diverges).then_some(Type::Unit), |e| self.expr(e));
+        self.items = outer;
+        t
+    }
+
+    fn block(&mut self, instrs: &[Instruction], tail: Option<&Expression>) -> Option<Type> {
+        self.scopes.push(HashMap::new());
+        let t = self.items(instrs, tail);
+        self.scopes.pop();
+        t
+    }
+
+    /// checks the body of a loop, returns the `break`s leaving it if it is a `loop`
+    fn loop_body(&mut self, label: &Option<Identifier>, body: &Expression, with_value: bool) -> Option<Breaks> {
+        self.loops.push((label.clone(), with_value.then(Vec::new)));
+        self.expr(body);
+        self.loops.pop().unwrap().1
+    }
+
+    /// the `break`s of a `loop`, or the arms of a `match`, give values of the same type,
+    /// that of the first one whose type is known
+    fn same_type(&mut self, breaks: Breaks) -> Option<Type> {
+        let expected = breaks.iter().find_map(|(_, t)| t.clone())?;
+        for (e, t) in breaks { self.expect(&e, t, &expected) }
+        Some(expected)
+    }
+
+    /// type of an `if` whose branches give values of types `t1` and `t2`, the `else` branch
+    /// gives a value of the type of the `if` branch: without `else`, or with an `else` of
+    /// type `()`, the `if` branch gives `()`
+    fn branches(&mut self, cond_true: &Expression, t1: Option<Type>, cond_false: &Expression, t2: Option<Type>) -> Option<Type> {
+        match (t1, t2) {
+            (Some(t1), Some(Type::Unit)) if t1 != Type::Unit => {
+                self.mismatch(cond_true.tail_expr().unwrap_or(cond_true), Type::Unit, t1);
+                Some(Type::Unit)
+            },
+            (Some(t1), t2) => {
+                self.expect(cond_false.tail_expr().unwrap_or(cond_false), t2, &t1);
+                Some(t1)
+            },
+            (None, t2) => t2,
+        }
+    }
+
+    fn function(&mut self, fun: &Function) {
+        // `break` ne traverse pas les fonctions
+        let loops = std::mem::take(&mut self.loops);
+        self.frames.push((self.scopes.len(), fun.ret.clone()));
+        self.scopes.push(fun.params.iter().map(|param| (param.id.clone(), Some(param.r#type.erased()))).collect());
+        let t = self.expr(&fun.body);
+        // `fn f() -> u8 { 1 }` : le littéral final prend le type de retour
+        if let Expression::Block(_, Some(tail)) = &*fun.body {
+            let t = match &fun.ret {
+                Type::Int(r) if tail.untyped_literal().is_some() => Some(Type::Int(*r)),
+                _ => t,
+            };
+            self.expect(tail, t, &fun.ret);
+        }
+        self.scopes.pop();
+        self.frames.pop();
+        self.loops = loops;
+    }
+
+    fn instr(&mut self, instr: &Instruction) {
+        use Instruction::*;
+        match instr {
+            Expr(e) => { self.expr(e); },
+            Free(p) => {
+                if let Some(t) = self.expr(p).filter(|t| *t != Type::Pointer) { self.mismatch(p, Type::Pointer, t) }
+            },
+            Break(label, e) => {
+                let t = e.as_ref().map_or(Some(Type::Unit), |e| self.expr(e));
+                let target = self.loops.iter_mut().rev().find(|(l, _)| label.is_none() || l == label);
+                match target {
+                    Some((_, Some(breaks))) => breaks.push((e.clone().unwrap_or(Expression::Const(ParsedValue::Unit)), t)),
+                    Some((_, None)) if e.is_some() => self.errors.push(EvalError::BreakWithValue),
+                    _ => (),
+                }
+            },
+            Let { pattern, r#type, expr } => {
+                let t = self.expr_as(expr, r#type.as_ref());
+                if let Some(expected) = r#type { self.expect(expr, t.clone(), expected) }
+                self.bind(pattern, r#type.clone().or(t));
+            },
+            While { label, cond, body } => {
+                let t = self.expr(cond);
+                self.expect(cond, t, &Type::Bool);
+                self.loop_body(label, body, false);
+            },
+            For { label, id, iter, body } => {
+                let t = self.expr(iter);
+                if let Some(t) = t.clone().filter(|t| !matches!(t, Type::Range(_))) { self.mismatch(iter, Type::Range(IntType::Isize), t) }
+                self.scopes.push(HashMap::new());
+                // la variable a le type des bornes
+                self.declare(id, match t {
+                    Some(Type::Range(t)) => Some(Type::Int(t)),
+                    _ => None,
+                });
+                self.loop_body(label, body, false);
+                self.scopes.pop();
+            },
+            WriteAt(place, op, e) => self.write_at(instr, place, *op, e),
+            FnDef(fun) => self.function(fun),
+            // `return 1` n'est pas converti au type de retour, comme à l'exécution
+            Return(Some(e)) => {
+                let t = self.expr(e);
+                if let Some((_, ret)) = self.frames.last().cloned() { self.expect(e, t, &ret) }
+            },
+            StructDef(_) | EnumDef(_) | Continue(_) | Return(None) => (),
+        }
+    }
+
+    /// `place = e` or `place op= e`: a value keeps the type of the place it is written in
+    fn write_at(&mut self, instr: &Instruction, place: &Expression, op: Option<Binop>, e: &Expression) {
+        if !place.is_place() { self.errors.push(EvalError::InvalidAssignment(place.clone())) }
+        let t1 = self.expr(place);
+        let t2 = match e.untyped_literal() {
+            Some(_) => self.expr_as(e, t1.as_ref()),
+            None => self.expr(e),
+        };
+        let Some(op) = op else {
+            if let Some(t1) = t1 { self.expect(e, t2, &t1) }
+            return
+        };
+        // les erreurs de `place op= e` désignent toute l'instruction
+        let mismatch = |expected, found| TypeMismatch { expression: Some(instr.clone().into()), expected, found: Some(found) };
+        let error = match (t1, t2) {
+            (Some(Type::Int(_)), Some(Type::Int(_))) if matches!(op, Binop::Shl | Binop::Shr) => None,
+            (Some(Type::String), Some(t)) if op == Binop::Add && !matches!(t, Type::Str | Type::String | Type::Pointer) => { self.mismatch(e, Type::Str, t); None },
+            (Some(Type::String), _) if op == Binop::Add => None,
+            (Some(Type::Bool), Some(t)) if is_bitwise(op) && t != Type::Bool => Some(mismatch(Type::Bool, t)),
+            (Some(t1 @ Type::Int(_)), Some(t2)) if t1 != t2 => Some(mismatch(t1, t2)),
+            (Some(t1), _) if !matches!(t1, Type::Int(_) | Type::Bool | Type::String)
+                || t1 == Type::Bool && !is_bitwise(op) || t1 == Type::String && op != Binop::Add => Some(mismatch(ISIZE, t1)),
+            _ => None,
+        };
+        self.errors.extend(error);
+    }
+
+    /// declares the variables of the pattern, matched against a value of type `t`
+    fn bind(&mut self, pattern: &Pattern, t: Option<Type>) {
+        match pattern {
+            Pattern::Identifier { id, .. } => self.declare(id, t),
+            Pattern::Tuple(ps) => {
+                let ts = match t {
+                    Some(Type::Tuple(ts)) if ts.len() == ps.len() => ts.into_iter().map(Some).collect(),
+                    _ => vec![None; ps.len()],
+                };
+                ps.iter().zip(ts).for_each(|(p, t)| self.bind(p, t));
+            },
+            Pattern::Struct { name, fields, .. } => {
+                let defs = self.find_struct(name).map(|def| def.fields.clone()).unwrap_or_default();
+                self.bind_fields(fields, &defs);
+            },
+            Pattern::Variant(name, variant, Some(p)) => {
+                let def = self.find_enum(name);
+                let fields = def.as_ref().and_then(|def| def.variants.iter().find(|v| v.name == *variant)).map(|v| &v.fields);
+                match (&**p, fields) {
+                    (Pattern::Tuple(ps), Some(Fields::Tuple(ts))) if ps.len() == ts.len() =>
+                        ps.iter().zip(ts).for_each(|(p, t)| self.bind(p, self.known(t))),
+                    (Pattern::Struct { fields, .. }, Some(Fields::Struct(defs))) => self.bind_fields(fields, defs),
+                    (Pattern::Struct { fields, .. }, _) => self.bind_fields(fields, &[]),
+                    (p, _) => self.bind(p, None),
+                }
+            },
+            Pattern::Variant(_, _, None) | Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range { .. } => (),
+        }
+    }
+
+    fn bind_fields(&mut self, fields: &[(Identifier, Pattern)], defs: &[(Identifier, Type)]) {
+        for (id, p) in fields {
+            let t = defs.iter().find(|(f, _)| f == id).and_then(|(_, t)| self.known(t));
+            self.bind(p, t);
+        }
+    }
+
+    /// type of `e` where a value of type `expected` is expected, as `eval_as` evaluates it
+    fn expr_as(&mut self, e: &Expression, expected: Option<&Type>) -> Option<Type> {
+        use Expression::*;
+        match (e, expected) {
+            (_, Some(Type::Int(t))) if e.untyped_literal().is_some() => Some(Type::Int(*t)),
+            (Tuple(es), Some(Type::Tuple(ts))) if es.len() == ts.len() =>
+                es.iter().zip(ts).map(|(e, t)| self.expr_as(e, Some(t))).collect::<Vec<_>>().into_iter().collect::<Option<_>>().map(Type::Tuple),
+            (Array(es), Some(Type::Array(t, _))) => self.array(es, Some(t)),
+            (ArrayRepeat(e, n), Some(Type::Array(t, _))) => self.repeat(e, n, Some(t)),
+            // sous une annotation, les éléments d'un `vec!` et la cible d'un `&` sont connus
+            (VecMacro(inner), Some(Type::VecOf(t))) => match self.expr_as(inner, Some(&Type::Array(t.clone(), 0))) {
+                // un `vec![]` vide est de tout type
+                Some(Type::Array(t, n)) if n > 0 => Some(Type::VecOf(t)),
+                _ => Some(Type::Vec),
+            },
+            (AmpersAnd(inner), Some(Type::PointerTo(_))) => Some(self.expr(inner).map_or(Type::Pointer, |t| Type::PointerTo(Box::new(t)))),
+            _ => self.expr(e),
+        }
+    }
+
+    /// `[a, b, c]`, the elements have the type of the first one
+    fn array(&mut self, es: &[Expression], expected: Option<&Type>) -> Option<Type> {
+        let Some((first, rest)) = es.split_first() else { return Some(Type::Array(Box::new(Type::Unit), 0)) };
+        let t = self.expr_as(first, expected);
+        for e in rest {
+            let found = self.expr_as(e, t.as_ref());
+            if let Some(t) = &t { self.expect(e, found, t) }
+        }
+        t.map(|t| Type::Array(Box::new(t), es.len()))
+    }
+
+    /// `[e; n]`, the length is only known if `n` is a literal
+    fn repeat(&mut self, e: &Expression, n: &Expression, expected: Option<&Type>) -> Option<Type> {
+        let t = self.expr_as(e, expected);
+        self.expect_int(n);
+        let len = n.int_literal().and_then(|(len, _)| usize::try_from(len).ok())?;
+        t.map(|t| Type::Array(Box::new(t), len))
+    }
+
+    /// types of the operands, an integer literal without suffix gets the type of the other one
+    fn operands(&mut self, lhs: &Expression, rhs: &Expression) -> (Option<Type>, Option<Type>) {
+        if rhs.untyped_literal().is_some() {
+            let t1 = self.expr(lhs);
+            let t2 = self.expr_as(rhs, t1.as_ref());
+            (t1, t2)
+        } else if lhs.untyped_literal().is_some() {
+            let t2 = self.expr(rhs);
+            (self.expr_as(lhs, t2.as_ref()), t2)
+        } else {
+            (self.expr(lhs), self.expr(rhs))
+        }
+    }
+
+    /// operands of an arithmetic operator, integers of the same type
+    fn int_operands(&mut self, lhs: &Expression, rhs: &Expression, t1: Option<Type>, t2: Option<Type>) -> Option<Type> {
+        match (t1, t2) {
+            (Some(t1), _) if !matches!(t1, Type::Int(_)) => { self.mismatch(lhs, ISIZE, t1); None },
+            (Some(t1), Some(t2)) if t1 != t2 => { self.mismatch(rhs, t1, t2); None },
+            (Some(t1), _) => Some(t1),
+            (None, t2) => t2.filter(|t| matches!(t, Type::Int(_))),
+        }
+    }
+
+    fn binop(&mut self, lhs: &Expression, op: Binop, rhs: &Expression) -> Option<Type> {
+        match op {
+            Binop::And | Binop::Or => {
+                for e in [lhs, rhs] {
+                    let t = self.expr(e);
+                    self.expect(e, t, &Type::Bool);
+                }
+                Some(Type::Bool)
+            },
+            Binop::Shl | Binop::Shr => {
+                let t = self.expr(lhs);
+                self.expect_int(rhs);
+                match t {
+                    Some(t) if !matches!(t, Type::Int(_)) => { self.mismatch(lhs, ISIZE, t); None },
+                    t => t,
+                }
+            },
+            op if is_comparison(op) => {
+                let (t1, t2) = self.operands(lhs, rhs);
+                let ordered = !matches!(op, Binop::Eq | Binop::Neq);
+                if let (Some(t1), Some(t2)) = (t1, t2) {
+                    match t1 {
+                        Type::Pointer | Type::Range(_) if !comparable(&t1, &t2, ordered) => self.mismatch(rhs, ISIZE, t1),
+                        _ if !comparable(&t1, &t2, ordered) => self.mismatch(rhs, t1, t2),
+                        _ => (),
+                    }
+                }
+                Some(Type::Bool)
+            },
+            // `&`, `|` et `^` s'appliquent aux entiers et aux booléens
+            op if is_bitwise(op) => {
+                let (t1, t2) = self.operands(lhs, rhs);
+                match (t1, t2) {
+                    (Some(t1 @ (Type::Int(_) | Type::Bool)), Some(t2)) if t1 != t2 => { self.mismatch(rhs, t1, t2); None },
+                    (Some(t1 @ (Type::Int(_) | Type::Bool)), _) => Some(t1),
+                    (Some(t1), _) => { self.mismatch(lhs, ISIZE, t1); None },
+                    (None, t2) => t2.filter(|t| matches!(t, Type::Int(_) | Type::Bool)),
+                }
+            },
+            // `s + t` concatène une `String` et un texte
+            Binop::Add => {
+                let (t1, t2) = self.operands(lhs, rhs);
+                match t1 {
+                    Some(Type::String) => {
+                        if let Some(t2) = t2.filter(|t| !matches!(t, Type::Str | Type::String | Type::Pointer)) { self.mismatch(rhs, Type::Str, t2) }
+                        Some(Type::String)
+                    },
+                    Some(Type::Str) => { self.mismatch(lhs, Type::String, Type::Str); None },
+                    t1 => self.int_operands(lhs, rhs, t1, t2),
+                }
+            },
+            _ => {
+                let (t1, t2) = self.operands(lhs, rhs);
+                self.int_operands(lhs, rhs, t1, t2)
+            },
+        }
+    }
+
+    fn method_call(&mut self, e: &Expression, receiver: &Expression, method: &Identifier, args: &[Expression]) -> Option<Type> {
+        let t = self.expr(receiver);
+        let name = method.to_string();
+        let res = match (&t, name.as_str()) {
+            (None, _) => None,
+            (Some(Type::Range(t)), "rev") if self.args_count(method, args, 0) => Some(Type::Range(*t)),
+            (Some(Type::Range(t)), "step_by") if self.args_count(method, args, 1) => { self.expect_int(&args[0]); Some(Type::Range(*t)) },
+            (Some(Type::Int(t)), name) if int_method(name).is_some() && self.args_count(method, args, 1) => {
+                let found = self.expr_as(&args[0], Some(&Type::Int(*t)));
+                self.expect(&args[0], found, &Type::Int(*t));
+                match int_method(name) {
+                    Some(("checked", _)) => Some(Type::Named(Identifier::from("Option"))),
+                    _ => Some(Type::Int(*t)),
+                }
+            },
+            (Some(Type::Array(..)), "len") if self.args_count(method, args, 0) => Some(USIZE),
+            (Some(Type::Vec), "len" | "capacity") if self.args_count(method, args, 0) => Some(USIZE),
+            (Some(Type::Vec), "push") if self.args_count(method, args, 1) => { self.expr(&args[0]); Some(Type::Unit) },
+            (Some(Type::Vec), "insert") if self.args_count(method, args, 2) => { self.expect_int(&args[0]); self.expr(&args[1]); Some(Type::Unit) },
+            (Some(Type::Vec), "pop") if self.args_count(method, args, 0) => Some(Type::Named(Identifier::from("Option"))),
+            // le type des éléments d'un `Vec` est inconnu
+            (Some(Type::Vec), "remove") if self.args_count(method, args, 1) => { self.expect_int(&args[0]); None },
+            (Some(Type::Str | Type::String), "len") if self.args_count(method, args, 0) => Some(USIZE),
+            (Some(Type::Str | Type::String | Type::Char), "to_string") if self.args_count(method, args, 0) => Some(Type::String),
+            (Some(t), "rev" | "step_by" | "len" | "capacity" | "push" | "insert" | "pop" | "remove" | "to_string")
+                if !matches!(t, Type::Int(_) | Type::Bool | Type::Unit | Type::Pointer | Type::Tuple(_) | Type::Named(_)) => None,
+            (Some(Type::Int(_)), name) if int_method(name).is_some() => None,
+            (Some(t), _) => {
+                self.errors.push(EvalError::UndefinedMethod { expression: e.clone(), method: method.clone(), r#type: t.clone() });
+                None
+            },
+        };
+        // les arguments des méthodes inconnues sont tout de même vérifiés
+        if t.is_none() { args.iter().for_each(|arg| { self.expr(arg); }) }
+        res
+    }
+
+    fn assoc_call(&mut self, r#type: &Identifier, id: &Identifier, args: &[Expression]) -> Option<Type> {
+        if self.find_enum(r#type).is_some() {
+            return self.variant(r#type, id, &Fields::Tuple(args.to_vec()))
+        }
+        match (r#type.to_string().as_str(), id.to_string().as_str()) {
+            ("String", "new") if self.args_count(id, args, 0) => Some(Type::String),
+            ("String", "from") if self.args_count(id, args, 1) => {
+                if let Some(t) = self.expr(&args[0]).filter(|t| !matches!(t, Type::Str | Type::String)) { self.mismatch(&args[0], Type::Str, t) }
+                Some(Type::String)
+            },
+            ("Vec", "new") if self.args_count(id, args, 0) => Some(Type::Vec),
+            ("Vec", "with_capacity") if self.args_count(id, args, 1) => { self.expect_int(&args[0]); Some(Type::Vec) },
+            _ => { args.iter().for_each(|arg| { self.expr(arg); }); None },
+        }
+    }
+
+    /// fields `f: e` of a struct literal or of a variant, of the types of their definition
+    fn fields(&mut self, defs: &[(Identifier, Type)], inits: &[(Identifier, Expression)]) {
+        for (id, e) in inits {
+            match defs.iter().find(|(f, _)| f == id).and_then(|(_, t)| self.known(t)) {
+                Some(expected) => {
+                    let t = self.expr_as(e, Some(&expected));
+                    self.expect(e, t, &expected);
+                },
+                None => { self.expr(e); },
+            }
+        }
+    }
+
+    fn variant(&mut self, name: &Identifier, variant: &Identifier, fields: &Fields<Expression>) -> Option<Type> {
+        let def = self.find_enum(name);
+        let defs = def.as_ref().and_then(|def| def.variants.iter().find(|v| v.name == *variant)).map(|v| &v.fields);
+        match (defs, fields) {
+            (Some(Fields::Tuple(ts)), Fields::Tuple(es)) if ts.len() == es.len() => {
+                for (t, e) in ts.iter().zip(es) {
+                    match self.known(t) {
+                        Some(expected) => {
+                            let t = self.expr_as(e, Some(&expected));
+                            self.expect(e, t, &expected);
+                        },
+                        None => { self.expr(e); },
+                    }
+                }
+            },
+            (Some(Fields::Struct(defs)), Fields::Struct(inits)) => self.fields(defs, inits),
+            (_, Fields::Tuple(es)) => es.iter().for_each(|e| { self.expr(e); }),
+            (_, Fields::Struct(inits)) => inits.iter().for_each(|(_, e)| { self.expr(e); }),
+            (_, Fields::Unit) => (),
+        }
+        Some(Type::Named(name.clone()))
+    }
+
+    fn expr(&mut self, e: &Expression) -> Option<Type> {
+        use Expression::*;
+        match e {
+            _ if e.int_literal().is_some() => Some(Type::Int(e.int_literal().unwrap().1.unwrap_or_default())),
+            Const(v) => Some(Type::from(&Value::from(v.clone()))),
+            Identifier(id) => self.lookup(id).unwrap_or_else(|e| { self.errors.push(e); None }),
+            BinOp(lhs, op, rhs) => self.binop(lhs, *op, rhs),
+            UnOp(Unop::Neg, inner) => match self.expr(inner) {
+                Some(t) if !matches!(t, Type::Int(_)) => { self.mismatch(inner, ISIZE, t); None },
+                t => t,
+            },
+            UnOp(Unop::Not, inner) => match self.expr(inner) {
+                Some(t) if !matches!(t, Type::Int(_) | Type::Bool) => { self.mismatch(inner, Type::Bool, t); None },
+                t => t,
+            },
+            Block(instrs, tail) => self.block(instrs, tail.as_deref()),
+            IfElse { cond, cond_true, cond_false } => {
+                let t = self.expr(cond);
+                self.expect(cond, t, &Type::Bool);
+                let t1 = self.expr(cond_true);
+                let t2 = self.expr(cond_false);
+                self.branches(cond_true, t1, cond_false, t2)
+            },
+            Call(id, args) => {
+                let Some(fun) = self.find_function(id) else {
+                    self.errors.push(EvalError::Undefined(id.clone()));
+                    args.iter().for_each(|arg| { self.expr(arg); });
+                    return None
+                };
+                if self.args_count(id, args, fun.params.len()) {
+                    for (param, arg) in fun.params.iter().zip(args) {
+                        let t = self.expr_as(arg, Some(&param.r#type));
+                        self.expect(arg, t, &param.r#type);
+                    }
+                } else {
+                    args.iter().for_each(|arg| { self.expr(arg); });
+                }
+                Some(fun.ret.erased())
+            },
+            Loop { label, body } => {
+                let breaks = self.loop_body(label, body, true).unwrap();
+                self.same_type(breaks)
+            },
+            // les bornes sont des entiers du même type, comme les opérandes de `+`
+            Range { start, end, .. } => {
+                let (t1, t2) = self.operands(start, end);
+                match self.int_operands(start, end, t1, t2) {
+                    Some(Type::Int(t)) => Some(Type::Range(t)),
+                    _ => None,
+                }
+            },
+            MethodCall(receiver, method, args) => self.method_call(e, receiver, method, args),
+            AssocCall(r#type, id, args) => self.assoc_call(r#type, id, args),
+            Index(array, index) => {
+                let t = self.expr(array);
+                self.expect_int(index);
+                match t {
+                    Some(Type::Array(t, _)) => Some(*t),
+                    // un texte est indexé par octet
+                    Some(Type::Str | Type::String) => Some(Type::Int(IntType::U8)),
+                    Some(Type::Pointer | Type::Vec) | None => None,
+                    Some(t) => { self.mismatch(e, Type::Str, t); None },
+                }
+            },
+            Tuple(es) => es.iter().map(|e| self.expr(e)).collect::<Vec<_>>().into_iter().collect::<Option<_>>().map(Type::Tuple),
+            Array(es) => self.array(es, None),
+            ArrayRepeat(inner, n) => self.repeat(inner, n, None),
+            VecMacro(inner) => { self.expr(inner); Some(Type::Vec) },
+            Struct(name, fields) => {
+                let defs = self.find_struct(name).map(|def| def.fields.clone()).unwrap_or_default();
+                self.fields(&defs, fields);
+                Some(Type::Named(name.clone()))
+            },
+            Variant(name, variant, fields) => self.variant(name, variant, fields),
+            Match(scrutinee, arms) => {
+                let t = self.expr(scrutinee);
+                let mut types = vec![];
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind(&arm.pattern, t.clone());
+                    if let Some(guard) = &arm.guard {
+                        let t = self.expr(guard);
+                        self.expect(guard, t, &Type::Bool);
+                    }
+                    let t = self.expr(&arm.body);
+                    types.push((arm.body.tail_expr().unwrap_or(&arm.body).clone(), t));
+                    self.scopes.pop();
+                }
+                match check_match(self.mem, &self.items, scrutinee, arms, t.as_ref()) {
+                    Ok(warnings) => self.warnings.extend(warnings),
+                    Err(e) => { self.non_exhaustive.get_or_insert(e); },
+                }
+                self.same_type(types)
+            },
+            Field(inner, field) => match self.expr(inner) {
+                Some(Type::Tuple(ts)) if field.to_string().parse::<usize>().is_ok_and(|i| i < ts.len()) =>
+                    Some(ts[field.to_string().parse::<usize>().unwrap()].clone()),
+                Some(Type::Named(name)) if self.find_struct(&name).is_some_and(|def| def.fields.iter().any(|(f, _)| f == field)) => {
+                    let def = self.find_struct(&name).unwrap();
+                    self.known(&def.fields.iter().find(|(f, _)| f == field).unwrap().1).as_ref().map(Type::erased)
+                },
+                // `p.f` est `(*p).f`
+                Some(Type::Pointer) | None => None,
+                Some(t) => {
+                    self.errors.push(EvalError::UndefinedField { expression: e.clone(), field: field.clone(), r#type: t });
+                    None
+                },
+            },
+            NewPtr => Some(Type::Pointer),
+            Deref(p) => {
+                if let Some(t) = self.expr(p).filter(|t| *t != Type::Pointer) { self.mismatch(e, Type::Pointer, t) }
+                None
+            },
+            AmpersAnd(inner) => { self.expr(inner); Some(Type::Pointer) },
+        }
+    }
+}
+
+impl<'a> Checker<'a> {
+    fn new(mem: &'a Memory) -> Self {
+        Checker {
+            mem,
+            items: mem.items().clone(),
+            scopes: vec![HashMap::new()],
+            frames: vec![],
+            loops: vec![],
+            errors: vec![],
+            warnings: vec![],
+            non_exhaustive: None,
+        }
+    }
+}
+
+fn check(mem: &Memory, instrs: &[Instruction], tail: Option<&Expression>) -> Result<Vec<UnreachableArm>, Error> {
+    let mut checker = Checker::new(mem);
+    checker.items(instrs, tail);
+    if !checker.errors.is_empty() { return Err(Error::TypeErrors(checker.errors)) }
+    match checker.non_exhaustive {
+        Some(e) => Err(Error::EvalError(e)),
+        None => Ok(checker.warnings),
+    }
+}
+
+impl Expression {
+    /// type of `self` inferred without evaluating it, with the variables of `mem` in scope:
+    /// none if it is only known at runtime
+    pub fn type_of(&self, mem: &Memory) -> Result<Option<Type>, Error> {
+        let mut checker = Checker::new(mem);
+        let t = checker.expr(self);
+        if !checker.errors.is_empty() { return Err(Error::TypeErrors(checker.errors)) }
+        Ok(t)
+    }
+}
+
+impl Program {
+    /// checks the types, then every `match`, before running: every type error found is
+    /// returned, else a value that no arm matches is an error, and the arms that can never
+    /// match are returned as warnings
+    pub fn check_types(&self, mem: &Memory) -> Result<Vec<UnreachableArm>, Error> {
+        check(mem, &self.instrs, self.tail.as_ref())
+    }
+}
+
+impl Instruction {
+    pub fn check_types(&self, mem: &Memory) -> Result<Vec<UnreachableArm>, Error> {
+        check(mem, std::slice::from_ref(self), None)
+    }
+}
+
+#[cfg(test)]
+mod test_typecheck {
+    use super::*;
+    use crate::parser::Parse;
+
+    /// type errors of `program`, displayed
+    fn errors(program: &str) -> Vec<String> {
+        match Program::parse(program).unwrap().check_types(&Memory::new()) {
+            Err(Error::TypeErrors(errors)) => errors.iter().map(|e| e.to_string()).collect(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        assert_eq!(errors("let v: Vec<bool> = vec![1, 2];"), vec!["Type mismatch in expression `vec![1, 2]`. Expected: Vec<bool>. Found: Vec<isize>"]);
+        assert_eq!(errors("fn f(p: &bool) {} let x = 1; f(&x);"), vec!["Type mismatch in expression `&x`. Expected: &bool. Found: &isize"]);
+        assert!(errors("let v: Vec<u8> = vec![1, 2]; let e: Vec<bool> = vec![]; v.len() + e.len()").is_empty());
+        assert!(errors("fn f(p: &isize) -> isize { *p } let x = 1; let p = &x; f(p) + f(&x)").is_empty());
+    }
+
+    #[test]
+    fn test_if_branches() {
+        assert_eq!(errors("let c = true; let x = if c { 1 } else { false };"), vec!["Type mismatch in expression `false`. Expected: isize. Found: bool"]);
+        assert_eq!(errors("let c = true; if c { 1 } else { };"), vec!["Type mismatch in expression `1`. Expected: unit. Found: isize"]);
+        assert_eq!(errors("let c = true; if c { 1 };"), vec!["Type mismatch in expression `1`. Expected: unit. Found: isize"]);
+        assert!(errors("let c = true; let x = if c { 1 } else { 2 };").is_empty());
+    }
+
+    #[test]
+    fn test_match_arms() {
+        assert_eq!(errors("let x = 2; match x { 0 => 1, 1 => true, _ => 'c' }"),
+            vec!["Type mismatch in expression `true`. Expected: isize. Found: bool", "Type mismatch in expression `'c'`. Expected: isize. Found: char"]);
+        assert_eq!(errors("let x = 2; match x { 0 => { let y = 1; y } _ => { () } }"), vec!["Type mismatch in expression `()`. Expected: isize. Found: unit"]);
+        assert!(errors("let x = 2; match x { 0 => 1, _ => x + 1 }").is_empty());
+    }
+
+    #[test]
+    fn test_loop_breaks() {
+        assert_eq!(errors("let x = loop { if true { break 1; } break 'c'; };"), vec!["Type mismatch in expression `'c'`. Expected: isize. Found: char"]);
+        assert!(errors("let x = loop { if true { break 1; } break 2; };").is_empty());
+    }
+
+    #[test]
+    fn test_undefined() {
+        assert_eq!(errors("let c = true; if c { let q = undefined_fn(1) + true; }"), vec!["Undefined identifier `undefined_fn`."]);
+        assert_eq!(errors("let c = true; if c { let q = y + 1; }"), vec!["Undefined identifier `y`."]);
+        assert_eq!(errors("let y = 1; fn f() -> isize { y } f()"), vec!["Undefined identifier `y`."]);
+    }
+
+    #[test]
+    fn test_free() {
+        assert_eq!(errors("free(5);"), vec!["Type mismatch in expression `5`. Expected: pointer. Found: isize"]);
+        assert_eq!(errors("let s = String::from(\"a\"); free(s);"), vec!["Type mismatch in expression `s`. Expected: pointer. Found: String"]);
+        assert!(errors("let p = Ptr::new(); let x = 1; free(p); free(&x);").is_empty());
+    }
+}
@@ -1,13 +1,70 @@
 use std::fmt;
+use std::rc::Rc;
 use crate::pointer::Pointer;
-use crate::{parsing::parsedvalue::ParsedValue, r#type::Type};
+use crate::range::Range;
+use crate::identifier::Identifier;
+use crate::{parsing::parsedvalue::ParsedValue, r#type::{Type, IntType}};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
-    Integer(isize),
+    /// the value always fits in the type
+    Integer(i128, IntType),
     Boolean(bool),
     Unit,
     Pointer(Pointer),
+    Range(Range),
+    Char(char),
+    /// a string literal `&str`
+    Str(Rc<str>),
+    /// an owned `String`, its text is in the heap cell
+    String(Pointer),
+    /// an owned `Vec`, it points to the heap cell describing its buffer
+    Vec(Pointer),
+    Tuple(Vec<Value>),
+    /// `[a, b, c]`, a fixed-size array
+    Array(Vec<Value>),
+    /// fields in declaration order
+    Struct(Identifier, Vec<(Identifier, Value)>),
+    /// `Enum::Variant`, its fields are `()`, a tuple, or a struct named after the variant
+    Variant(Identifier, Identifier, Box<Value>),
+}
+
+/// how the components of a tuple or of a struct make up its value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Tuple,
+    Array,
+    Struct(Identifier, Vec<Identifier>),
+}
+
+impl Shape {
+    pub fn build(&self, vs: Vec<Value>) -> Value {
+        match self {
+            Shape::Tuple => Value::Tuple(vs),
+            Shape::Array => Value::Array(vs),
+            Shape::Struct(name, fields) => Value::Struct(name.clone(), fields.iter().cloned().zip(vs).collect()),
+        }
+    }
+
+    /// type of a value of this shape whose components have the types `ts`
+    pub fn r#type(&self, ts: Vec<Type>) -> Type {
+        match self {
+            Shape::Tuple => Type::Tuple(ts),
+            // comme pour une valeur, un tableau vide a des éléments de type `()`
+            Shape::Array => Type::Array(Box::new(ts.first().cloned().unwrap_or(Type::Unit)), ts.len()),
+            Shape::Struct(name, _) => Type::Named(name.clone()),
+        }
+    }
+
+    /// position of a component: `0`, `1`, ... for a tuple, a field name for a struct,
+    /// the elements of an array are only reached by indexing
+    pub fn index(&self, field: &Identifier) -> Option<usize> {
+        match self {
+            Shape::Tuple => field.to_string().parse().ok(),
+            Shape::Array => None,
+            Shape::Struct(_, fields) => fields.iter().position(|f| f == field),
+        }
+    }
 }
 
 
@@ -15,10 +72,24 @@ impl fmt::Display for Value {
 
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Value::Integer(i) => write!(f, "{}", i),
+            Value::Integer(i, _) => write!(f, "{}", i),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Unit => write!(f, "()"),
-            Value::Pointer(p) => write!(f, "{}", p)
+            Value::Pointer(p) => write!(f, "{}", p),
+            Value::Range(r) => write!(f, "{}", r),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::String(p) => write!(f, "String({})", p),
+            Value::Vec(p) => write!(f, "Vec({})", p),
+            Value::Tuple(vs) if vs.len() == 1 => write!(f, "({},)", vs[0]),
+            Value::Tuple(vs) => write!(f, "({})", vs.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")),
+            Value::Array(vs) => write!(f, "[{}]", vs.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")),
+            Value::Struct(name, fields) => write!(f, "{} {{ {} }}", name, fields.iter().map(|(id, v)| format!("{}: {}", id, v)).collect::<Vec<_>>().join(", ")),
+            Value::Variant(name, variant, fields) => match &**fields {
+                Value::Unit => write!(f, "{}::{}", name, variant),
+                Value::Tuple(vs) => write!(f, "{}::{}({})", name, variant, vs.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")),
+                fields => write!(f, "{}::{}", name, fields),
+            },
         }
     }
 }
@@ -26,9 +97,12 @@ impl fmt::Display for Value {
 impl From<ParsedValue> for Value {
     fn from(pv: ParsedValue) -> Self {
         match pv {
-            ParsedValue::Integer(i) => Value::Integer(i),
+            // un littéral sans suffixe est un `isize`, qu'il doit pouvoir contenir
+            ParsedValue::Integer(i, t) => Value::Integer(i, t.unwrap_or_default()),
             ParsedValue::Boolean(b) => Value::Boolean(b),
             ParsedValue::Unit => Value::Unit,
+            ParsedValue::Char(c) => Value::Char(c),
+            ParsedValue::Str(s) => Value::Str(s),
         }
     }
 }
@@ -36,19 +110,36 @@ impl From<ParsedValue> for Value {
 impl From<&Value> for Type {
     fn from(v: &Value) -> Self {
         match v {
-            Value::Integer(_) => Type::Int,
+            Value::Integer(_, t) => Type::Int(*t),
             Value::Boolean(_) => Type::Bool,
             Value::Unit => Type::Unit,
-            Value::Pointer(_) => Type::Pointer
+            Value::Pointer(_) => Type::Pointer,
+            Value::Range(r) => Type::Range(r.int_type()),
+            Value::Char(_) => Type::Char,
+            Value::Str(_) => Type::Str,
+            Value::String(_) => Type::String,
+            Value::Vec(_) => Type::Vec,
+            Value::Tuple(vs) => Type::Tuple(vs.iter().map(Type::from).collect()),
+            // le type des éléments d'un tableau vide est inconnu, `()` par défaut
+            Value::Array(vs) => Type::Array(Box::new(vs.first().map_or(Type::Unit, Type::from)), vs.len()),
+            Value::Struct(name, _) | Value::Variant(name, _, _) => Type::Named(name.clone()),
         }
     }
 }
 
 #[allow(unused)]
 impl Value {
+    /// `Option::Some(v)` or `Option::None`
+    pub fn option(v: Option<Value>) -> Value {
+        match v {
+            Some(v) => Value::Variant(Identifier::from("Option"), Identifier::from("Some"), Box::new(Value::Tuple(vec![v]))),
+            None => Value::Variant(Identifier::from("Option"), Identifier::from("None"), Box::new(Value::Unit)),
+        }
+    }
+    /// an integer of any type, as an `isize`
     pub fn to_int(&self) -> Result<isize, Type> {
         match self {
-            Value::Integer(i) => Ok(*i),
+            Value::Integer(i, _) => isize::try_from(*i).map_err(|_| Type::from(self)),
             _ => Err(Type::from(self)),
         }
     }
@@ -58,4 +149,42 @@ impl Value {
             _ => Err(Type::from(self)),
         }
     }
+    /// true if the value owns heap memory: it is moved, not copied, and dropped at the end of its scope
+    pub fn is_owned(&self) -> bool {
+        match self {
+            Value::String(_) | Value::Vec(_) => true,
+            Value::Tuple(vs) | Value::Array(vs) => vs.iter().any(|v| v.is_owned()),
+            Value::Struct(_, fields) => fields.iter().any(|(_, v)| v.is_owned()),
+            Value::Variant(_, _, fields) => fields.is_owned(),
+            _ => false,
+        }
+    }
+    /// the owned values making up the value: itself, or the owned components of a tuple
+    pub fn owned_parts(&self) -> Vec<Value> {
+        match self {
+            Value::String(_) | Value::Vec(_) => vec![self.clone()],
+            Value::Tuple(vs) | Value::Array(vs) => vs.iter().flat_map(|v| v.owned_parts()).collect(),
+            Value::Struct(_, fields) => fields.iter().flat_map(|(_, v)| v.owned_parts()).collect(),
+            Value::Variant(_, _, fields) => fields.owned_parts(),
+            _ => vec![],
+        }
+    }
+    /// the components of a tuple, of an array or of a struct, other values are given back
+    pub fn into_components(self) -> Result<(Shape, Vec<Value>), Value> {
+        match self {
+            Value::Tuple(vs) => Ok((Shape::Tuple, vs)),
+            Value::Array(vs) => Ok((Shape::Array, vs)),
+            Value::Struct(name, fields) => {
+                let (ids, vs) = fields.into_iter().unzip();
+                Ok((Shape::Struct(name, ids), vs))
+            },
+            v => Err(v),
+        }
+    }
+    pub fn to_range(&self) -> Result<Range, Type> {
+        match self {
+            Value::Range(r) => Ok(*r),
+            _ => Err(Type::from(self)),
+        }
+    }
 }
\ No newline at end of file
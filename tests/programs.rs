@@ -0,0 +1,266 @@
+// PROGRAMMES µRust EXÉCUTÉS PAR `microrust run`
+// chaque test vérifie la valeur affichée d'un programme, ou l'erreur qu'il signale
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// runs a µRust program with `microrust run`
+fn run(name: &str, program: &str) -> Output {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.rs", name));
+    std::fs::write(&path, program).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_microrust")).arg("run").arg(&path).output().unwrap()
+}
+
+/// the value printed by a program that succeeds
+fn eval(name: &str, program: &str) -> String {
+    let out = run(name, program);
+    assert!(out.status.success(), "{}: {}", name, String::from_utf8_lossy(&out.stderr));
+    String::from_utf8_lossy(&out.stdout).trim().to_string()
+}
+
+/// the error reported by a program that fails, without crashing the interpreter
+fn error(name: &str, program: &str) -> String {
+    let out = run(name, program);
+    assert_eq!(out.status.code(), Some(1), "{}: {}", name, String::from_utf8_lossy(&out.stdout));
+    String::from_utf8_lossy(&out.stderr).trim().to_string()
+}
+
+/// what the REPL prints for the lines of `input`
+fn repl(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_microrust")).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let out = child.wait_with_output().unwrap();
+    String::from_utf8_lossy(&out.stdout).into_owned() + &String::from_utf8_lossy(&out.stderr)
+}
+
+// FONCTIONS
+
+#[test]
+fn test_callee_scope() {
+    // l'appelé ne voit pas les variables locales de l'appelant, ni les globales
+    assert_eq!(error("caller_local", "fn f(n: isize) -> isize { let x = 5; g(n) } fn g(n: isize) -> isize { x + n } f(1)"),
+        "Type Error: Undefined identifier `x`.");
+    assert_eq!(error("global", "let x = 1; fn f() -> isize { x } f()"), "Type Error: Undefined identifier `x`.");
+    // une fonction n'est visible que dans le bloc qui la déclare, y compris avant sa déclaration
+    assert_eq!(error("block_fn", "{ fn g() -> isize { 1 } } g()"), "Type Error: Undefined identifier `g`.");
+    assert_eq!(eval("nested_fn", "fn f(n: isize) -> isize { sq(n) + 1 } fn sq(n: isize) -> isize { n * n } f(3)"), "10");
+    assert_eq!(eval("fn_and_var", "let x = 1; fn x() -> isize { 2 } x() + x"), "3");
+}
+
+// ENTIERS
+
+#[test]
+fn test_literal_tail() {
+    // le littéral final prend le type de retour, mais ne remplace pas la valeur d'un `return`
+    assert_eq!(eval("literal_tail", "fn f() -> u8 { 255 } f()"), "255");
+    assert_eq!(eval("return_before_tail", "fn f(n: isize) -> isize { if n > 0 { return n * 10; } 0 } f(4) + f(-1)"), "40");
+}
+
+#[test]
+fn test_arithmetic_overflow() {
+    assert_eq!(error("add_u8", "255u8 + 1"), "Evaluation Error: Arithmetic overflow in `(255u8 + 1)`.");
+    assert_eq!(error("sub_u8", "0u8 - 1"), "Evaluation Error: Arithmetic overflow in `(0u8 - 1)`.");
+    assert_eq!(error("mul_i32", "2i32 * 1_500_000_000"), "Evaluation Error: Arithmetic overflow in `(2i32 * 1500000000)`.");
+    assert_eq!(error("div_min", "let a = -9223372036854775808i64; a / -1"), "Evaluation Error: Arithmetic overflow in `(a / -1)`.");
+    assert_eq!(error("rem_min", "let a = -9223372036854775808i64; a % -1"), "Evaluation Error: Arithmetic overflow in `(a % -1)`.");
+    assert_eq!(error("neg_min", "let a = -128i8; -a"), "Evaluation Error: Arithmetic overflow in `-a`.");
+    // un littéral hors des bornes du type annoncé
+    assert_eq!(error("literal_u8", "let x: u8 = 256;"), "Evaluation Error: Arithmetic overflow in `256`.");
+    assert_eq!(error("literal_i8", "let x: i8 = -129;"), "Evaluation Error: Arithmetic overflow in `-129`.");
+    assert_eq!(eval("bounds", "let a: i8 = -128; let b: u64 = 18446744073709551615; (a, b)"), "(-128, 18446744073709551615)");
+}
+
+#[test]
+fn test_int_methods() {
+    assert_eq!(eval("wrapping", "(250u8.wrapping_add(10), 5u8.wrapping_sub(10), 200u8.wrapping_mul(2), 100i8.wrapping_add(100))"), "(4, 251, 144, -56)");
+    assert_eq!(eval("checked", "(250u8.checked_add(10), 250u8.checked_add(5), 10i8.checked_div(0), 7u8.checked_rem(0), 100i8.checked_mul(2))"),
+        "(Option::None, Option::Some(255), Option::None, Option::None, Option::None)");
+    assert_eq!(eval("saturating", "(250u8.saturating_add(10), (-100i8).saturating_sub(100), 0usize.saturating_sub(1), 200u8.saturating_mul(2))"), "(255, -128, 0, 255)");
+}
+
+// RETURN, BREAK ET CONTINUE
+
+#[test]
+fn test_labeled_loops() {
+    // `continue 'outer` et `break 'outer` traversent la boucle intérieure
+    assert_eq!(eval("labeled", "let mut n = 0; 'outer: while n < 10 { let mut m = 0; while m < 10 { m = m + 1; if m == 3 { continue 'outer; } if n == 5 { break 'outer; } n = n + 1; } } n"), "5");
+    assert_eq!(eval("continue", "let mut s = 0; let mut i = 0; while i < 5 { i = i + 1; if i % 2 == 0 { continue; } s = s + i; } s"), "9");
+    assert_eq!(error("unknown_label", "'a: while true { while true { break 'b; } }"), "Evaluation Error: `break 'b` outside of a loop labeled `'b`.");
+    assert_eq!(error("break_outside", "break;"), "Evaluation Error: `break` outside of a loop.");
+    // `break` et `continue` ne traversent pas les appels de fonction
+    assert_eq!(error("continue_in_fn", "fn f() { continue; } while true { f(); }"), "Evaluation Error: `continue` outside of a loop.");
+}
+
+#[test]
+fn test_return() {
+    assert_eq!(eval("return_in_while", "fn f(n: isize) -> isize { let mut i = 0; while true { if i == n { return i * 10; } i = i + 1; } i } f(4)"), "40");
+    assert_eq!(eval("return_in_for", "fn f(n: isize) -> isize { let none = -1; for i in 0..3 { if i == n { return i; } } none } (f(2), f(5))"), "(2, -1)");
+    assert_eq!(eval("return_unit", "fn f(n: isize) { if n > 0 { return; } } f(1)"), "");
+}
+
+// LOOP
+
+#[test]
+fn test_loop_value() {
+    assert_eq!(eval("loop_value", "fn f(n: isize) -> isize { let mut i = 0; loop { i = i + 1; if i * i > n { break i; } } } (f(10), f(100))"), "(4, 11)");
+    // les `break` de la boucle sont typés avant l'exécution : la boucle ne tourne pas
+    assert_eq!(error("loop_breaks", "let mut i = 0; let x = loop { i = i + 1; if i > 3 { break 'c'; } break 1; };"),
+        "Type Error: Type mismatch in expression `1`. Expected: char. Found: isize");
+}
+
+// OPÉRATEURS
+
+#[test]
+fn test_short_circuit() {
+    // `f()` échoue s'il est évalué : `&&` et `||` n'évaluent pas leur opérande droit quand le gauche suffit
+    let f = "fn f() -> bool { let a = [1, 2]; let i = 2; a[i] == 1 }";
+    assert_eq!(eval("and_short", &format!("{} false && f()", f)), "false");
+    assert_eq!(eval("or_short", &format!("{} true || f()", f)), "true");
+    assert!(error("and_evaluated", &format!("{} true && f()", f)).contains("out of bounds"));
+}
+
+#[test]
+fn test_operators() {
+    assert_eq!(eval("precedence", "(-2 * 3, 1 + 2 << 3, 6 & 3 == 2, true || false && false)"), "(-6, 24, true, true)");
+    assert_eq!(error("chained", "let a = 1; a < 2 < 3"), "Parse Error: Operators `<` and `<` cannot be chained, use parentheses");
+}
+
+// IF ET ELSE
+
+#[test]
+fn test_if_without_else() {
+    assert_eq!(eval("else_if", "fn sign(n: isize) -> isize { if n < 0 { -1 } else if n == 0 { 0 } else { 1 } } (sign(-5), sign(0), sign(7))"), "(-1, 0, 1)");
+    assert_eq!(eval("no_else", "let mut x = 0; if x == 0 { x = 1; } x"), "1");
+    assert_eq!(error("no_else_value", "let x = if true { 1 };"), "Type Error: Type mismatch in expression `1`. Expected: unit. Found: isize");
+    // le type d'un élément de `Vec` n'est connu qu'à l'exécution
+    assert_eq!(error("no_else_vec", "let v = vec![1]; let x = if true { v[0] };"), "Evaluation Error: Type mismatch in expression `v[0]`. Expected: unit. Found: isize");
+}
+
+// REPL
+
+#[test]
+fn test_repl_type() {
+    // `:type` n'évalue pas l'expression : ni boucle infinie, ni calcul, ni effet de bord
+    let out = repl("let mut x = 3u8;\nfn fib(n: isize) -> isize { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }\n:type fib(40)\n:type loop {}\n:type { x = 4; x + 1 }\nx\n:type y\n");
+    assert!(out.contains("fib(40) : isize"), "{}", out);
+    assert!(out.contains("loop {} : _"), "{}", out);
+    assert!(out.contains("{x = 4;(x + 1)} : u8"), "{}", out);
+    assert!(out.contains("- : u8 = 3"), "{}", out);
+    assert!(out.contains("Type Error: Undefined identifier `y`."), "{}", out);
+}
+
+// CARACTÈRES ET CHAÎNES
+
+#[test]
+fn test_escapes() {
+    assert_eq!(eval("str_escapes", r#"let s = "a\tb\n\"c\"\\\x41\u{e9}"; s"#), r#""a\tb\n\"c\"\\Aé""#);
+    assert_eq!(eval("char_escapes", r"('\n', '\'', '\x41', '\u{1F600}')"), r"('\n', '\'', 'A', '😀')");
+    assert_eq!(error("bad_escape", r#""\q""#), r"Parse Error: Invalid escape or character literal `\q`");
+}
+
+#[test]
+fn test_strings() {
+    // la longueur et les indices comptent les octets de l'UTF-8
+    assert_eq!(eval("bytes", r#"let s = String::from("héllo"); (s.len(), s[0], s[1])"#), "(6, 104, 195)");
+    assert_eq!(error("byte_bounds", r#"let s = String::from("abc"); s[3]"#), "Evaluation Error: Index out of bounds in `s[3]`: the len is 3 but the index is 3.");
+    assert_eq!(eval("concat", r#"let s = String::from("ab"); let t = s + "cd"; t"#), r#""abcd""#);
+    assert_eq!(error("moved", r#"let s = String::from("ab"); let t = s; s"#), "Evaluation Error: `s` has been moved");
+}
+
+// TUPLES
+
+#[test]
+fn test_tuples() {
+    assert_eq!(eval("destructure", "let (a, (b, c)) = (1, (true, 3)); (a, b, c)"), "(1, true, 3)");
+    assert_eq!(eval("field_write", "let mut t = (1, 2); t.1 = 5; let (x, y) = t; x + y"), "6");
+    // `&t.1` pointe dans la cellule du tuple
+    assert_eq!(eval("field_pointer", "let mut t = (1, (2, 3)); let p = &t.1; (*p).0 = 9; let q = &t.0; *q = 4; t"), "(4, (9, 3))");
+    assert_eq!(error("arity", "let (a, b) = (1, 2, 3);"), "Evaluation Error: Pattern `(a, b)` does not match `(1, 2, 3)` of type (isize, isize, isize).");
+    assert_eq!(error("no_field", "let t = (1, 2); t.2"), "Type Error: No field `2` on type (isize, isize) in `t.2`.");
+}
+
+// STRUCTURES
+
+#[test]
+fn test_struct_fields() {
+    // `(*p).f` et `p.f` écrivent dans la structure pointée
+    assert_eq!(eval("write_through", "struct P { x: isize, y: isize } let mut a = P { x: 1, y: 2 }; let p = &a; (*p).x = 10; p.y = 20; (a.x, a.y)"), "(10, 20)");
+    assert_eq!(eval("nested_write", "struct P { x: isize } struct L { a: P, b: P } let mut l = L { a: P { x: 1 }, b: P { x: 2 } }; l.b.x = 5; let p = &l.a; p.x = 7; l.a.x + l.b.x"), "12");
+    assert_eq!(error("immutable", "struct P { x: isize } let a = P { x: 1 }; a.x = 2;"), "Evaluation Error: Cell at `a.x` is not mutable.");
+    assert_eq!(error("missing", "struct P { x: isize, y: isize } let a = P { x: 1 };"), "Evaluation Error: Missing field `y` in `P { x: 1 }`.");
+    assert_eq!(error("field_type", "struct P { x: isize } let mut a = P { x: 1 }; a.x = true;"), "Type Error: Type mismatch in expression `true`. Expected: isize. Found: bool");
+}
+
+#[test]
+fn test_struct_scope() {
+    // une structure n'est visible que dans le bloc qui la déclare, où elle masque celles du dehors
+    assert_eq!(error("block_struct", "{ struct P { x: isize } } let a = P { x: 1 };"), "Evaluation Error: Undefined identifier `P`.");
+    assert_eq!(eval("shadowed_struct", "struct P { x: isize } let b = { struct P { y: bool } let b = P { y: true }; b.y }; let a = P { x: 1 }; (a.x, b)"), "(1, true)");
+}
+
+// ENUMS ET MATCH
+
+#[test]
+fn test_match_guards() {
+    let f = "enum E { A(isize), B { x: isize }, C } fn f(e: E) -> isize { match e { E::A(n) if n > 10 => n * 2, E::A(n) => n, E::B { x } if x < 0 => 0, E::B { x } => x + 100, E::C => -1 } }";
+    assert_eq!(eval("guards", &format!("{} (f(E::A(20)), f(E::A(3)), f(E::B {{ x: -5 }}), f(E::B {{ x: 5 }}), f(E::C))", f)), "(40, 3, 0, 105, -1)");
+    // la liaison d'un bras dont la garde échoue ne masque pas la variable du dehors
+    assert_eq!(eval("guard_scope", "let n = 1; match 5 { n if n > 10 => 0, _ => n }"), "1");
+    // la garde voit la valeur liée sans la déplacer hors du bras suivant
+    assert_eq!(eval("guard_move", r#"let o = Option::Some(String::from("a")); match o { Option::Some(t) if t.len() > 5 => 0usize, Option::Some(t) => t.len(), Option::None => 0usize }"#), "1");
+}
+
+#[test]
+fn test_enums() {
+    assert_eq!(error("non_exhaustive", "enum E { A, B } let e = E::A; match e { E::A => 1 }"), "Evaluation Error: Non-exhaustive patterns in `match e`: pattern `E::B` not covered.");
+    assert_eq!(error("no_variant", "enum E { A } E::Z"), "Evaluation Error: Undefined identifier `E::Z`.");
+    assert_eq!(eval("equality", "enum E { A(isize), B } (E::A(1) == E::A(1), E::A(1) == E::A(2), E::A(1) == E::B)"), "(true, false, false)");
+}
+
+// TABLEAUX
+
+#[test]
+fn test_arrays() {
+    assert_eq!(eval("element_write", "let mut a = [0; 3]; a[1] = 5; let p = &a[2]; *p = 7; (a, a.len())"), "([0, 5, 7], 3)");
+    assert_eq!(eval("nested", "let a = [[1, 2], [3, 4]]; a[1][0]"), "3");
+    assert_eq!(error("elements", "let a = [1, true];"), "Type Error: Type mismatch in expression `true`. Expected: isize. Found: bool");
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    assert_eq!(error("past_end", "let a = [1, 2, 3]; let i = 3; a[i]"), "Evaluation Error: Index out of bounds in `a[i]`: the len is 3 but the index is 3.");
+    assert_eq!(error("negative", "let a = [1, 2]; let i = -1; a[i]"), "Evaluation Error: Index out of bounds in `a[i]`: the len is 2 but the index is -1.");
+    assert_eq!(error("write_past_end", "let mut a = [1, 2]; a[2] = 3;"), "Evaluation Error: Index out of bounds in `a[2]`: the len is 2 but the index is 2.");
+}
+
+// VEC ET POINTEURS
+
+#[test]
+fn test_vec() {
+    assert_eq!(eval("insert_remove", "let mut v = vec![1, 2, 3]; v.insert(1, 9); let r = v.remove(0); (r, v[0], v[1], v.len())"), "(1, 9, 2, 3)");
+    assert_eq!(eval("pop", "let mut v = vec![7]; let a = v.pop(); let b = v.pop(); (a, b, v.len())"), "(Option::Some(7), Option::None, 0)");
+    assert_eq!(error("insert_bounds", "let mut v = vec![1, 2]; v.insert(3, 5);"), "Evaluation Error: Index out of bounds in `v.insert(3, 5)`: the len is 2 but the index is 3.");
+    assert_eq!(error("remove_bounds", "let mut v = vec![1, 2]; v.remove(2);"), "Evaluation Error: Index out of bounds in `v.remove(2)`: the len is 2 but the index is 2.");
+}
+
+#[test]
+fn test_vec_reallocation() {
+    // la capacité double quand le tampon est plein
+    assert_eq!(eval("capacity", "let mut v = Vec::new(); let mut cs = [0usize; 6]; let mut i = 0usize; while i < 6 { cs[i] = v.capacity(); v.push(i); i = i + 1; } (cs, v.capacity())"),
+        "([0, 4, 4, 4, 4, 8], 8)");
+    // le `push` qui réalloue libère l'ancien tampon : un pointeur vers un élément est invalidé
+    assert_eq!(error("stale_pointer", "let mut v = vec![1, 2]; let p = &v[0]; v.push(3); *p"), "Evaluation Error: `p` is a use after free.");
+    assert_eq!(eval("pointer_in_capacity", "let mut v = Vec::with_capacity(4); v.push(1); let p = &v[0]; v.push(2); *p = 5; v[0]"), "5");
+}
+
+#[test]
+fn test_free() {
+    assert_eq!(error("double_free", "let p = Ptr::new(); *p = 1; free(p); free(p);"), "Evaluation Error: `p` is a use after free.");
+    // `free` d'une variable, d'une partie de valeur ou d'un non-pointeur est une erreur, pas un plantage
+    assert_eq!(error("free_var", "let x = 1; free(&x);"), "Evaluation Error: cannot free `&x`, not allocated by `Ptr::new()`");
+    assert_eq!(error("free_field", "let t = (1, 2); free(&t.1);"), "Evaluation Error: cannot free `&t.1`, not allocated by `Ptr::new()`");
+    assert_eq!(error("free_owned", r#"let s = String::from("a"); free(s);"#), "Type Error: Type mismatch in expression `s`. Expected: pointer. Found: String");
+    assert_eq!(error("free_int", "free(5);"), "Type Error: Type mismatch in expression `5`. Expected: pointer. Found: isize");
+    // le type d'un élément de `Vec` n'est connu qu'à l'exécution
+    assert_eq!(error("free_element", "let v = vec![5]; free(v[0]);"), "Evaluation Error: Type mismatch in expression `v[0]`. Expected: pointer. Found: isize");
+}
@@ -0,0 +1,51 @@
+// RÉCURSION JUSQU'À LA PROFONDEUR D'APPEL MAXIMALE
+// l'interpréteur doit atteindre `--max-depth` (1000 par défaut) sans déborder la pile
+// hôte, y compris dans un build de debug, puis lever `StackOverflow` au-delà
+
+use std::process::{Command, Output};
+
+/// runs a µRust program with `microrust run`
+fn run(name: &str, program: &str) -> Output {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.rs", name));
+    std::fs::write(&path, program).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_microrust")).arg("run").arg(&path).output().unwrap()
+}
+
+/// `f(999)` is 1000 nested calls, the limit, and `f(1000)` one too many
+fn check_limit(name: &str, def: &str) {
+    let out = run(name, &format!("{}\nf(999)", def));
+    assert!(out.status.success(), "{}: {}", name, String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "999");
+    let out = run(&format!("{}_over", name), &format!("{}\nf(1000)", def));
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Stack overflow: call depth exceeds 1000"));
+}
+
+#[test]
+fn test_non_tail_call() {
+    check_limit("non_tail", "fn f(n: isize) -> isize { if n == 0 { 0 } else { 1 + f(n - 1) } }");
+}
+
+#[test]
+fn test_match_arm() {
+    check_limit("match_arm", "fn f(n: isize) -> isize { match n { 0 => 0, n => 1 + f(n - 1) } }");
+}
+
+#[test]
+fn test_for_body() {
+    check_limit("for_body", "fn f(n: isize) -> isize { let mut s = 0; for _i in 0..1 { if n > 0 { s = 1 + f(n - 1); } } s }");
+}
+
+#[test]
+fn test_infinite_recursion() {
+    let out = run("infinite", "fn f(n: isize) -> isize { 1 + f(n - 1) }\nf(1)");
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Stack overflow"));
+}
+
+/// chaque appel imbrique ici un bloc, un `if`, un `match` et un `while` : la pile par appel
+/// prévue en debug couvre aussi ce corps, le plus profond mesuré
+#[test]
+fn test_deeply_nested_body() {
+    check_limit("nested_body", "fn f(n: isize) -> isize { let x = { let y = if n > 0 { match n { m => { let mut t = 0; while t < 1 { t = t + 1 + f(m - 1); } t } } } else { 0 }; y }; x }");
+}